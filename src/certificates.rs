@@ -1,8 +1,39 @@
-use openssl::x509::X509Name;
+use core::fmt;
+use std::{collections::{BTreeMap, HashSet}, net::IpAddr, str::FromStr};
+
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    error::ErrorStack,
+    hash::{hash, MessageDigest},
+    pkey::{PKey, PKeyRef, Public},
+    x509::X509Name
+};
 use serde::{Deserialize, Serialize};
 
-use crate::key::Key;
+use crate::{
+    certificates::{
+        access::AccessUrls,
+        constraints::NameConstraints,
+        extensions::CustomExtension,
+        policies::{Policy, PolicyConstraints},
+        serial::SerialStrategy,
+        signature::{Digest, Signature}
+    },
+    key::{Key, KeyType}
+};
 
+pub mod access;
+pub mod chain;
+pub mod constraints;
+pub mod crl;
+pub mod csr;
+pub mod der;
+pub mod extensions;
+pub mod ocsp;
+pub mod policies;
+pub mod serial;
+pub mod signature;
 pub mod x509;
 
 
@@ -16,11 +47,204 @@ pub struct X509Info {
 
 
 pub struct CertArgs {
+    /// Issuer name, `None` for self-signed certificates
     pub authority_issuer: Option<X509Name>,
-    pub authority_pkey: Option<Key>,
-    pub key: Key,
+    /// Signing key, the certificate own key when self-signed
+    pub authority_pkey: Key,
+    pub public_key: PKey<Public>,
     pub name: String,
-    pub cert_entries: X509Info
+    pub subject: Subject,
+    pub not_before: Asn1Time,
+    pub not_after: Asn1Time,
+    /// Signature made with the authority key, or the certificate key for roots
+    pub signature: Signature,
+    pub subject_key_id: Vec<u8>,
+    /// Key identifier of the signing key, `None` for self-signed certificates
+    pub authority_key_id: Option<Vec<u8>>,
+    pub san: Option<AltNames>,
+    pub profile: Profile,
+    pub serial: BigNum,
+    /// Constraints of an authority certificate, ignored for leaves
+    pub path_len: Option<u32>,
+    pub name_constraints: Option<NameConstraints>,
+    /// Resolved URLs of the issuer CRL, OCSP responder and certificate
+    pub access_urls: AccessUrls,
+    /// Policies of the certificate, its own or inherited
+    pub policies: Vec<Policy>,
+    pub policy_constraints: Option<PolicyConstraints>,
+    pub inhibit_any_policy: Option<u32>,
+    /// Extensions of the specification, appended after the ones pkimgr sets
+    pub extensions: Vec<CustomExtension>
+}
+
+
+/// Subject DN attributes of a certificate, besides its common name. Attributes
+/// left out fall back to the configuration `x509_certs_entries`, an empty value
+/// removes them.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct Subject {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locality: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub organizational_unit: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<String>,
+    /// Domain components, most significant first: `["com", "example"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domain_components: Vec<String>,
+    /// Additional RDNs, each one holding one or more attributes by short name or OID
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rdns: Vec<BTreeMap<String, String>>,
+}
+
+
+impl Subject {
+    /// This subject, completed with the configuration defaults. A default is left out when a
+    /// multi-valued RDN holds its attribute.
+    pub fn resolve(&self, defaults: &X509Info) -> Subject {
+        let or_default = |value: &Option<String>, field: &str, default: &str| match self.in_rdns(field) {
+            true => value.clone(),
+            false => Some(value.as_deref().unwrap_or(default).to_string()).filter(|value| !value.is_empty())
+        };
+
+        Subject {
+            country: or_default(&self.country, "C", &defaults.country),
+            state: or_default(&self.state, "ST", &defaults.state),
+            organization: or_default(&self.organization, "O", &defaults.organization),
+            ..self.clone()
+        }
+    }
+
+
+    fn in_rdns(&self, field: &str) -> bool {
+        self.rdns.iter().any(|rdn| rdn.contains_key(field))
+    }
+
+
+    /// Set an attribute by its short name, unknown ones are added as a single valued RDN
+    pub fn set(&mut self, field: &str, value: &str) {
+        let value = value.to_string();
+
+        match field {
+            "C" => self.country = Some(value),
+            "ST" => self.state = Some(value),
+            "L" => self.locality = Some(value),
+            "O" => self.organization = Some(value),
+            "OU" => self.organizational_unit.push(value),
+            "emailAddress" => self.email = Some(value),
+            "serialNumber" => self.serial_number = Some(value),
+            "DC" => self.domain_components.push(value),
+            _ => self.rdns.push(BTreeMap::from([(field.to_string(), value)])),
+        }
+    }
+
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(country) = &self.country {
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(format!("country must be two uppercase letters, got {:?}", country));
+            }
+        }
+
+        if let Some(email) = &self.email {
+            if !is_email(email) {
+                return Err(format!("invalid email address {:?}", email));
+            }
+        }
+
+        if let Some(serial_number) = &self.serial_number {
+            let printable = |c: char| c.is_ascii_alphanumeric() || " '()+,-./:=?".contains(c);
+
+            if serial_number.is_empty() || !serial_number.chars().all(printable) {
+                return Err(format!("serial number must be a non empty printable string, got {:?}", serial_number));
+            }
+        }
+
+        for dc in self.domain_components.iter() {
+            if dc.is_empty() || dc.contains('.') || !dc.is_ascii() {
+                return Err(format!("invalid domain component {:?}", dc));
+            }
+        }
+
+        if self.rdns.iter().any(|rdn| rdn.is_empty()) {
+            return Err("RDNs must hold at least one attribute".to_string());
+        }
+
+        if self.in_rdns("CN") {
+            return Err("the common name cannot be part of an RDN".to_string());
+        }
+
+        let single = [
+            ("C", &self.country),
+            ("ST", &self.state),
+            ("L", &self.locality),
+            ("O", &self.organization),
+            ("emailAddress", &self.email),
+            ("serialNumber", &self.serial_number),
+        ];
+
+        for (field, value) in single {
+            if value.is_some() && self.in_rdns(field) {
+                return Err(format!("{} is set both alone and in a multi-valued RDN", field));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Subject Alternative Name entries of a certificate
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct AltNames {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ip: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub email: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uri: Vec<String>,
+}
+
+
+impl AltNames {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(ip) = self.ip.iter().find(|ip| IpAddr::from_str(ip).is_err()) {
+            return Err(format!("invalid IP address {:?}", ip));
+        }
+
+        if let Some(email) = self.email.iter().find(|email| !is_email(email)) {
+            return Err(format!("invalid email address {:?}", email));
+        }
+
+        for uri in self.uri.iter() {
+            let valid = uri.split_once(':').is_some_and(|(scheme, rest)| {
+                scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                    && !rest.is_empty()
+            });
+
+            if !valid || uri.chars().any(|c| c.is_whitespace() || !c.is_ascii()) {
+                return Err(format!("invalid URI {:?}", uri));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+fn is_email(email: &str) -> bool {
+    email.is_ascii() && email.split_once('@').is_some_and(|(user, domain)| !user.is_empty() && !domain.is_empty())
 }
 
 
@@ -28,8 +252,264 @@ pub struct CertArgs {
 pub struct Certificate {
     pub cname: String,
     pub subcerts: Vec<Certificate>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ca: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub keylen: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub curve: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Subject>,
+    /// Validity in days, overriding the configuration one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity: Option<u32>,
+    /// Start of validity as `YYYY-MM-DD` or `YYYYMMDDHHMMSSZ`, now by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<String>,
+    /// End of validity, same formats as `not_before`, takes precedence over `validity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<String>,
+    /// The private key is held by the requester of a signed CSR, only the certificate is stored
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub external_key: bool,
+    /// Root authority signed by an issuer outside of this PKI, from a generated CSR
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub external_issuer: bool,
+    /// Allow this certificate to expire after its issuer
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub outlive_issuer: bool,
+    /// Signature digest, overriding the PKI one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<Digest>,
+    /// Sign with RSASSA-PSS, overriding the PKI setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rsa_pss: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub san: Option<AltNames>,
+    #[serde(default, alias = "usage", skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    /// Maximum number of authorities below this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_len: Option<u32>,
+    /// Names allowed and forbidden to the certificates issued below this authority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_constraints: Option<NameConstraints>,
+    /// CRL, OCSP and issuer URLs of the certificates issued below this authority, overriding the PKI ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_urls: Option<AccessUrls>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policies: Vec<Policy>,
+    /// Certificates below this authority without policies of their own assert its policies
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inherit_policies: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_constraints: Option<PolicyConstraints>,
+    /// Number of certificates below this authority before anyPolicy stops matching
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inhibit_any_policy: Option<u32>,
+    /// How this authority draws the serial numbers of the certificates it issues, at random by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serials: Option<SerialStrategy>,
+    /// Extensions added as they are to the certificate
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<CustomExtension>,
+    /// Certificates previously issued for this common name, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PreviousCertificate>,
+}
+
+
+/// Certificate replaced by a renewal or a re-key
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PreviousCertificate {
+    /// Hexadecimal serial number
+    pub serial: String,
+    pub not_after: String,
+    /// The key was replaced along with the certificate
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rekeyed: bool,
+}
+
+
+impl Certificate {
+    /// Check this specification and its sub certificates, before any key is generated.
+    /// Errors name the certificate and the field at fault.
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_tree(&mut HashSet::new())
+    }
+
+
+    fn validate_tree<'a>(&'a self, names: &mut HashSet<&'a str>) -> Result<(), String> {
+        let invalid = |field: &str, err: String| format!("{}: {}: {}", self.cname, field, err);
+
+        if self.cname.is_empty() {
+            return Err("cname: a common name is required".to_string());
+        }
+
+        if !names.insert(&self.cname) {
+            return Err(invalid("cname", "duplicated common name".to_string()));
+        }
+
+        match (self.keylen, &self.curve) {
+            (Some(_), Some(_)) => return Err(invalid("keylen", "cannot be set along with curve".to_string())),
+            (_, Some(curve)) => {
+                curve.parse::<KeyType>().map_err(|err| invalid("curve", err))?;
+            },
+            _ => ()
+        }
+
+        if let Some(subject) = &self.subject {
+            subject.validate().map_err(|err| invalid("subject", err))?;
+        }
+
+        if let Some(san) = &self.san {
+            san.validate().map_err(|err| invalid("san", err))?;
+        }
+
+        if let Some(name_constraints) = &self.name_constraints {
+            name_constraints.validate().map_err(|err| invalid("name_constraints", err))?;
+        }
+
+        if let Some(access_urls) = &self.access_urls {
+            access_urls.validate().map_err(|err| invalid("access_urls", err))?;
+        }
+
+        for policy in self.policies.iter() {
+            policy.validate().map_err(|err| invalid("policies", err))?;
+        }
+
+        if let Some(policy_constraints) = &self.policy_constraints {
+            policy_constraints.validate().map_err(|err| invalid("policy_constraints", err))?;
+        }
+
+        let mut oids = HashSet::new();
+
+        for extension in self.extensions.iter() {
+            extension.validate().map_err(|err| invalid("extensions", err))?;
+
+            if !oids.insert(&extension.oid) {
+                return Err(invalid("extensions", format!("{} is set more than once", extension.oid)));
+            }
+        }
+
+        for cert in self.subcerts.iter() {
+            if cert.external_issuer {
+                return Err(format!("{}: external_issuer: only the root authority can be signed externally", cert.cname));
+            }
+
+            let constraints = cert.path_len.is_some() || cert.name_constraints.is_some() || cert.access_urls.is_some()
+                || cert.inherit_policies || cert.policy_constraints.is_some() || cert.inhibit_any_policy.is_some()
+                || cert.serials.is_some();
+
+            if cert.subcerts.is_empty() && !cert.ca && constraints {
+                return Err(format!(
+                    "{}: path_len, name_constraints, access_urls, inherit_policies, policy_constraints, \
+                    inhibit_any_policy and serials only apply to authorities",
+                    cert.cname
+                ));
+            }
+
+            cert.validate_tree(names)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Key usages of a leaf certificate
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    #[default]
+    TlsServer,
+    TlsClient,
+    Mtls,
+    CodeSigning,
+    EmailProtection,
+    OcspSigning,
+    Timestamping,
+}
+
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tls-server" => Ok(Profile::TlsServer),
+            "tls-client" => Ok(Profile::TlsClient),
+            "mtls" => Ok(Profile::Mtls),
+            "code-signing" => Ok(Profile::CodeSigning),
+            "email-protection" => Ok(Profile::EmailProtection),
+            "ocsp-signing" => Ok(Profile::OcspSigning),
+            "timestamping" => Ok(Profile::Timestamping),
+            _ => Err(format!("Unknown profile: {}", s))
+        }
+    }
+}
+
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Profile::TlsServer => write!(f, "tls-server"),
+            Profile::TlsClient => write!(f, "tls-client"),
+            Profile::Mtls => write!(f, "mtls"),
+            Profile::CodeSigning => write!(f, "code-signing"),
+            Profile::EmailProtection => write!(f, "email-protection"),
+            Profile::OcspSigning => write!(f, "ocsp-signing"),
+            Profile::Timestamping => write!(f, "timestamping"),
+        }
+    }
+}
+
+
+/// Method deriving the Subject and Authority Key Identifiers from a public key
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyIdentifier {
+    /// SHA-1 of the subject public key, RFC 5280 method 1
+    #[default]
+    Sha1,
+    /// `0100` followed by the last 60 bits of the SHA-1, RFC 5280 method 2
+    Sha1Short,
+    /// Leftmost 160 bits of the SHA-256, RFC 7093 method 1
+    Sha256,
+}
+
+
+impl KeyIdentifier {
+    pub fn compute(&self, key: &PKeyRef<Public>) -> Result<Vec<u8>, ErrorStack> {
+        let bits = KeyIdentifier::public_key_bits(key)?;
+
+        Ok(match self {
+            KeyIdentifier::Sha1 => hash(MessageDigest::sha1(), &bits)?.to_vec(),
+            KeyIdentifier::Sha1Short => {
+                let mut id = hash(MessageDigest::sha1(), &bits)?[12..].to_vec();
+                id[0] = 0x40 | (id[0] & 0x0f);
+                id
+            },
+            KeyIdentifier::Sha256 => hash(MessageDigest::sha256(), &bits)?[..20].to_vec(),
+        })
+    }
+
+
+    /// Content of the subjectPublicKey BIT STRING of a key, without its unused bits byte,
+    /// which key identifiers and OCSP key hashes are computed over
+    pub fn public_key_bits(key: &PKeyRef<Public>) -> Result<Vec<u8>, ErrorStack> {
+        let spki = key.public_key_to_der()?;
+        // SubjectPublicKeyInfo: algorithm, then the key bits after their unused bits count
+        let bits = der::Der::new(&spki).expect(der::TAG_SEQUENCE)
+            .and_then(|info| {
+                let mut fields = info.children();
+
+                fields.expect(der::TAG_SEQUENCE)?;
+                fields.expect(der::TAG_BIT_STRING)
+            })
+            .and_then(|bits| bits.content.get(1..))
+            .ok_or_else(ErrorStack::get)?;
+
+        Ok(bits.to_vec())
+    }
 }
@@ -0,0 +1,105 @@
+//! Where clients fetch the revocation data and issuer certificates of a certificate: its
+//! CRL Distribution Points and Authority Information Access extensions.
+use openssl::{error::ErrorStack, x509::X509Extension};
+use serde::{Deserialize, Serialize};
+
+use crate::certificates::{der, x509::der_extension};
+
+
+const CRL_DISTRIBUTION_POINTS_OID: &str = "2.5.29.31";
+const AUTHORITY_INFO_ACCESS_OID: &str = "1.3.6.1.5.5.7.1.1";
+const OCSP_OID: &str = "1.3.6.1.5.5.7.48.1";
+const CA_ISSUERS_OID: &str = "1.3.6.1.5.5.7.48.2";
+
+const PLACEHOLDERS: [&str; 2] = ["{pki}", "{issuer}"];
+
+
+/// URL templates of the certificates issued by an authority. `{pki}` is replaced by the
+/// PKI name and `{issuer}` by the issuing authority common name.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct AccessUrls {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crl: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ocsp: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_issuers: Vec<String>,
+}
+
+
+impl AccessUrls {
+    pub fn validate(&self) -> Result<(), String> {
+        for url in self.crl.iter().chain(&self.ocsp).chain(&self.ca_issuers) {
+            let Some((scheme, rest)) = url.split_once("://") else {
+                return Err(format!("{:?} is not an URL", url));
+            };
+
+            if scheme.is_empty() || rest.is_empty() || url.chars().any(|c| c.is_whitespace() || !c.is_ascii()) {
+                return Err(format!("{:?} is not an URL", url));
+            }
+
+            // Only the known placeholders may remain once replaced
+            let rest = PLACEHOLDERS.iter().fold(url.to_owned(), |url, placeholder| url.replace(placeholder, ""));
+
+            if rest.contains(['{', '}']) {
+                return Err(format!("{:?} has an unknown placeholder, expected {}", url, PLACEHOLDERS.join(" or ")));
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// URLs of the certificates issued by `issuer` on the `pki` PKI
+    pub fn resolve(&self, pki: &str, issuer: &str) -> AccessUrls {
+        let replace = |urls: &Vec<String>| urls.iter()
+            .map(|url| url.replace("{pki}", &encode(pki)).replace("{issuer}", &encode(issuer)))
+            .collect();
+
+        AccessUrls {
+            crl: replace(&self.crl),
+            ocsp: replace(&self.ocsp),
+            ca_issuers: replace(&self.ca_issuers)
+        }
+    }
+
+
+    /// CRL Distribution Points and Authority Information Access, when they have URLs
+    pub fn to_extensions(&self) -> Result<Vec<X509Extension>, ErrorStack> {
+        let uri = |url: &String| der::implicit(6, url.as_bytes());
+        let mut extensions = vec![];
+
+        if !self.crl.is_empty() {
+            // A single distribution point, its full name listing every URL
+            let full_name: Vec<u8> = self.crl.iter().flat_map(uri).collect();
+            let point = der::sequence(&[der::explicit(0, &der::explicit(0, &full_name))]);
+
+            extensions.push(der_extension(CRL_DISTRIBUTION_POINTS_OID, false, &der::sequence(&[point]))?);
+        }
+
+        let mut descriptions = vec![];
+
+        for (method, urls) in [(OCSP_OID, &self.ocsp), (CA_ISSUERS_OID, &self.ca_issuers)] {
+            let method = der::oid(method).ok_or_else(ErrorStack::get)?;
+
+            descriptions.extend(urls.iter().map(|url| der::sequence(&[method.clone(), uri(url)])));
+        }
+
+        if !descriptions.is_empty() {
+            extensions.push(der_extension(AUTHORITY_INFO_ACCESS_OID, false, &der::sequence(&descriptions))?);
+        }
+
+        Ok(extensions)
+    }
+}
+
+
+/// Percent-encode the characters of a placeholder value which are not allowed in an URL path
+fn encode(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte)
+        })
+        .collect()
+}
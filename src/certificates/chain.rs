@@ -0,0 +1,107 @@
+//! Checks of a certificate against its issuer. Openssl stops at the first error of a
+//! chain, these report every problem found on a link.
+use std::cmp::Ordering;
+
+use foreign_types::ForeignTypeRef;
+use openssl::{asn1::Asn1Time, error::ErrorStack, x509::X509Ref};
+use openssl_sys as ffi;
+
+
+/// Basic constraints of the certificate mark it as a CA
+pub fn is_ca(cert: &X509Ref) -> bool {
+    // X509_get_extension_flags only caches the decoded extensions of the certificate
+    unsafe { ffi::X509_get_extension_flags(cert.as_ptr()) & ffi::EXFLAG_CA != 0 }
+}
+
+
+/// Extended key usage of the certificate includes id-kp-OCSPSigning
+pub fn is_ocsp_signer(cert: &X509Ref) -> bool {
+    // Without the extension openssl reports every usage, the delegation must be explicit
+    unsafe {
+        ffi::X509_get_extension_flags(cert.as_ptr()) & ffi::EXFLAG_XKUSAGE != 0
+            && ffi::X509_get_extended_key_usage(cert.as_ptr()) & ffi::XKU_OCSP_SIGN != 0
+    }
+}
+
+
+/// Problems with the validity period of a certificate at the current time
+pub fn check_validity(cert: &X509Ref) -> Result<Vec<String>, ErrorStack> {
+    let now = Asn1Time::days_from_now(0)?;
+    let mut problems = vec![];
+
+    if cert.not_before().compare(&now)? == Ordering::Greater {
+        problems.push(format!("not valid before {}", cert.not_before()));
+    }
+
+    if cert.not_after().compare(&now)? == Ordering::Less {
+        problems.push(format!("expired on {}", cert.not_after()));
+    }
+
+    Ok(problems)
+}
+
+
+/// Problems with the link between a certificate and the issuer that signed it
+pub fn check_issued(cert: &X509Ref, issuer: &X509Ref) -> Result<Vec<String>, ErrorStack> {
+    let mut problems = vec![];
+    let issuer_key = issuer.public_key()?;
+
+    if cert.issuer_name().try_cmp(issuer.subject_name())? != Ordering::Equal {
+        problems.push("issuer name does not match the subject of its issuer".to_string());
+    }
+
+    if !cert.verify(&issuer_key)? {
+        problems.push("signature does not verify with the key of its issuer".to_string());
+    }
+
+    match (cert.authority_key_id(), issuer.subject_key_id()) {
+        (Some(aki), Some(ski)) if aki.as_slice() != ski.as_slice() => {
+            problems.push("authority key identifier does not match the subject key identifier of its issuer".to_string());
+        },
+        (Some(_), None) => {
+            problems.push("authority key identifier set, but its issuer has no subject key identifier".to_string());
+        },
+        _ => ()
+    }
+
+    if !is_ca(issuer) {
+        problems.push("issuer is not a CA".to_string());
+    }
+
+    // All usages are allowed without keyUsage extension
+    if unsafe { ffi::X509_get_key_usage(issuer.as_ptr()) } & ffi::X509v3_KU_KEY_CERT_SIGN == 0 {
+        problems.push("key usage of its issuer does not allow certificate signing".to_string());
+    }
+
+    Ok(problems)
+}
+
+
+/// Problems with the path length constraints of a chain, from a certificate up to its root
+pub fn check_path_length(chain: &[&X509Ref]) -> Result<Vec<String>, ErrorStack> {
+    let mut problems = vec![];
+
+    for (depth, issuer) in chain.iter().enumerate().skip(1) {
+        let Some(path_length) = issuer.pathlen() else {
+            continue;
+        };
+
+        // Self-issued certificates do not count, nor does the certificate checked
+        let mut intermediates = 0;
+
+        for cert in chain[1..depth].iter() {
+            if cert.issuer_name().try_cmp(cert.subject_name())? != Ordering::Equal {
+                intermediates += 1;
+            }
+        }
+
+        if intermediates > path_length {
+            problems.push(format!(
+                "{} intermediate CAs follow the issuer at depth {}, whose path length is {}",
+                intermediates, depth, path_length
+            ));
+        }
+    }
+
+    Ok(problems)
+}
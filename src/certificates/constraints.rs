@@ -0,0 +1,292 @@
+//! Name constraints of an authority, restricting the names of the certificates issued
+//! below it. The openssl crate has no builder for this extension, it is encoded here.
+use core::fmt;
+use std::net::IpAddr;
+
+use openssl::{
+    error::ErrorStack,
+    nid::Nid,
+    x509::{GeneralNameRef, X509Extension, X509Name, X509NameBuilder, X509NameRef, X509Ref}
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    certificates::{der, x509::der_extension},
+    report::name_to_string
+};
+
+
+const NAME_CONSTRAINTS_OID: &str = "2.5.29.30";
+
+
+/// Names of a subtree, as the matching Subject Alternative Name entries
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct Subtrees {
+    /// Domain and its subdomains, only the subdomains when starting with a dot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns: Vec<String>,
+    /// Networks in CIDR notation: `10.0.0.0/8`, `fd00::/8`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ip: Vec<String>,
+    /// Mailbox, every mailbox of a host, or of its subdomains when starting with a dot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub email: Vec<String>,
+    /// Distinguished names subjects must start with: `C=UK, O=Partner`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dir_name: Vec<String>,
+}
+
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct NameConstraints {
+    #[serde(default, skip_serializing_if = "Subtrees::is_empty")]
+    pub permitted: Subtrees,
+    #[serde(default, skip_serializing_if = "Subtrees::is_empty")]
+    pub excluded: Subtrees,
+}
+
+
+impl Subtrees {
+    pub fn is_empty(&self) -> bool {
+        self.dns.is_empty() && self.ip.is_empty() && self.email.is_empty() && self.dir_name.is_empty()
+    }
+
+
+    fn validate(&self) -> Result<(), String> {
+        let hostname = |value: &str| !value.is_empty() && value.split('.').skip(value.starts_with('.') as usize)
+            .all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+
+        for dns in self.dns.iter() {
+            if !hostname(dns) {
+                return Err(format!("invalid DNS name {:?}", dns));
+            }
+        }
+
+        for ip in self.ip.iter() {
+            parse_network(ip).ok_or_else(|| format!("invalid network {:?}, expected address/prefix", ip))?;
+        }
+
+        for email in self.email.iter() {
+            let host = email.rsplit_once('@').map_or(email.as_str(), |(_, host)| host);
+
+            if !hostname(host) || email.starts_with('@') {
+                return Err(format!("invalid email constraint {:?}", email));
+            }
+        }
+
+        for dir_name in self.dir_name.iter() {
+            parse_name(dir_name).map_err(|_| format!("invalid distinguished name {:?}", dir_name))?;
+        }
+
+        Ok(())
+    }
+
+
+    /// DER GeneralSubtrees content
+    fn to_der(&self) -> Result<Vec<u8>, ErrorStack> {
+        let mut bases = vec![];
+
+        bases.extend(self.email.iter().map(|email| der::implicit(1, email.as_bytes())));
+        bases.extend(self.dns.iter().map(|dns| der::implicit(2, dns.as_bytes())));
+
+        for dir_name in self.dir_name.iter() {
+            bases.push(der::explicit(4, &parse_name(dir_name)?.to_der()?));
+        }
+
+        for ip in self.ip.iter() {
+            let (address, prefix) = parse_network(ip).ok_or_else(ErrorStack::get)?;
+            let mut value = octets(&address);
+
+            value.extend(mask(value.len(), prefix));
+            bases.push(der::implicit(7, &value));
+        }
+
+        Ok(bases.iter().flat_map(|base| der::sequence(&[base.to_owned()])).collect())
+    }
+}
+
+
+impl NameConstraints {
+    pub fn validate(&self) -> Result<(), String> {
+        self.permitted.validate().map_err(|err| format!("permitted: {}", err))?;
+        self.excluded.validate().map_err(|err| format!("excluded: {}", err))
+    }
+
+
+    /// Critical extension, as RFC 5280 requires
+    pub fn to_extension(&self) -> Result<X509Extension, ErrorStack> {
+        let mut value = vec![];
+
+        if !self.permitted.is_empty() {
+            value.push(der::explicit(0, &self.permitted.to_der()?));
+        }
+
+        if !self.excluded.is_empty() {
+            value.push(der::explicit(1, &self.excluded.to_der()?));
+        }
+
+        der_extension(NAME_CONSTRAINTS_OID, true, &der::sequence(&value))
+    }
+
+
+    /// Check the names of a certificate, the error describes the first one out of the constraints
+    pub fn check(&self, cert: &X509Ref) -> Result<(), String> {
+        let alt_names = cert.subject_alt_names();
+        let alt_names: Vec<&GeneralNameRef> = alt_names.iter().flat_map(|names| names.iter()).collect();
+        let subject = cert.subject_name();
+
+        let mut dns: Vec<&str> = alt_names.iter().filter_map(|name| name.dnsname()).collect();
+        let mut email: Vec<&str> = alt_names.iter().filter_map(|name| name.email()).collect();
+        let ip: Vec<IpAddr> = alt_names.iter().filter_map(|name| name.ipaddress().and_then(from_octets)).collect();
+        let common_names = text_entries(subject, Nid::COMMONNAME);
+        let emails = text_entries(subject, Nid::PKCS9_EMAILADDRESS);
+
+        // Like openssl, a common name looking like a host name stands for it without DNS alternative names
+        if dns.is_empty() {
+            dns.extend(common_names.iter().map(String::as_str).filter(|cn| cn.contains('.') && !cn.contains(' ')));
+        }
+
+        email.extend(emails.iter().map(String::as_str));
+
+        check_names("DNS name", &dns, &self.permitted.dns, &self.excluded.dns, |name, dns| dns_matches(name, dns))?;
+        check_names("email", &email, &self.permitted.email, &self.excluded.email, |name, email| email_matches(name, email))?;
+        check_names("IP address", &ip, &self.permitted.ip, &self.excluded.ip, |address, ip| {
+            parse_network(ip).is_some_and(|(network, prefix)| ip_matches(address, &network, prefix))
+        })?;
+
+        if subject.entries().next().is_some() {
+            let subject_entries = entries(subject);
+
+            check_names("subject", &[name_to_string(subject)], &self.permitted.dir_name, &self.excluded.dir_name, |_, dir_name| {
+                parse_name(dir_name).is_ok_and(|name| subject_entries.starts_with(&entries(&name)))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+
+fn check_names<T: fmt::Display>(
+    kind: &str,
+    names: &[T],
+    permitted: &[String],
+    excluded: &[String],
+    matches: impl Fn(&T, &String) -> bool
+) -> Result<(), String> {
+    for name in names {
+        if let Some(subtree) = excluded.iter().find(|subtree| matches(name, subtree)) {
+            return Err(format!("{} {} is excluded by {}", kind, name, subtree));
+        }
+
+        if !permitted.is_empty() && !permitted.iter().any(|subtree| matches(name, subtree)) {
+            return Err(format!("{} {} is not permitted", kind, name));
+        }
+    }
+
+    Ok(())
+}
+
+
+fn dns_matches(name: &str, subtree: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let subtree = subtree.to_ascii_lowercase();
+
+    match subtree.starts_with('.') {
+        true => name.ends_with(&subtree),
+        false => name == subtree || name.ends_with(&format!(".{}", subtree))
+    }
+}
+
+
+fn email_matches(email: &str, subtree: &str) -> bool {
+    let Some((_, host)) = email.rsplit_once('@') else {
+        return false;
+    };
+
+    match (subtree.contains('@'), subtree.starts_with('.')) {
+        (true, _) => email.eq_ignore_ascii_case(subtree),
+        (false, true) => host.to_ascii_lowercase().ends_with(&subtree.to_ascii_lowercase()),
+        (false, false) => host.eq_ignore_ascii_case(subtree)
+    }
+}
+
+
+fn ip_matches(address: &IpAddr, network: &IpAddr, prefix: u8) -> bool {
+    let (address, network) = (octets(address), octets(network));
+
+    address.len() == network.len() && address.iter()
+        .zip(network.iter())
+        .zip(mask(network.len(), prefix))
+        .all(|((address, network), mask)| address & mask == network & mask)
+}
+
+
+/// Address and prefix length of a `address/prefix` network
+fn parse_network(value: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = value.split_once('/')?;
+    let address: IpAddr = address.parse().ok()?;
+    let prefix: u8 = prefix.parse().ok()?;
+
+    (usize::from(prefix) <= octets(&address).len() * 8).then_some((address, prefix))
+}
+
+
+fn octets(address: &IpAddr) -> Vec<u8> {
+    match address {
+        IpAddr::V4(address) => address.octets().to_vec(),
+        IpAddr::V6(address) => address.octets().to_vec(),
+    }
+}
+
+
+fn from_octets(octets: &[u8]) -> Option<IpAddr> {
+    match octets.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(octets).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(octets).ok()?)),
+        _ => None
+    }
+}
+
+
+fn mask(len: usize, prefix: u8) -> Vec<u8> {
+    (0..len)
+        .map(|index| {
+            let bits = usize::from(prefix).saturating_sub(index * 8).min(8);
+
+            (0xff00u16 >> bits) as u8
+        })
+        .collect()
+}
+
+
+/// Name from its `C=UK, O=Partner` form, most significant attribute first
+fn parse_name(value: &str) -> Result<X509Name, ErrorStack> {
+    let mut builder = X509NameBuilder::new()?;
+
+    for attribute in value.split(',') {
+        let (field, value) = attribute.split_once('=').ok_or_else(ErrorStack::get)?;
+
+        builder.append_entry_by_text(field.trim(), value.trim())?;
+    }
+
+    Ok(builder.build())
+}
+
+
+/// Attributes of a name, compared without case
+fn entries(name: &X509NameRef) -> Vec<(Nid, String)> {
+    name.entries()
+        .map(|entry| (
+            entry.object().nid(),
+            entry.data().as_utf8().map(|value| value.to_lowercase()).unwrap_or_default()
+        ))
+        .collect()
+}
+
+
+fn text_entries(name: &X509NameRef, nid: Nid) -> Vec<String> {
+    name.entries_by_nid(nid)
+        .filter_map(|entry| entry.data().as_utf8().ok().map(|value| value.to_string()))
+        .collect()
+}
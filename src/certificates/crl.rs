@@ -0,0 +1,221 @@
+use core::fmt;
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use foreign_types::{ForeignType, ForeignTypeRef};
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    error::ErrorStack,
+    x509::{X509Crl, X509Extension, X509}
+};
+use openssl_sys as ffi;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    certificates::{der, signature::{sign_crl, Signature}, x509::{authority_key_identifier, der_extension}},
+    key::Key
+};
+
+
+const CRL_REASON_OID: &str = "2.5.29.21";
+
+
+/// Revocations recorded against an authority
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct RevocationList {
+    pub crl_number: u64,
+    pub revoked: Vec<Revocation>,
+}
+
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Revocation {
+    /// Hexadecimal serial number of the revoked certificate
+    pub serial: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cname: Option<String>,
+    pub reason: RevocationReason,
+    /// Revocation date, as seconds since the Unix epoch
+    pub date: i64,
+}
+
+
+impl Revocation {
+    pub fn new(serial: &str, cname: Option<String>, reason: RevocationReason) -> Result<Revocation, ErrorStack> {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+
+        Ok(Revocation {
+            serial: BigNum::from_hex_str(serial)?.to_hex_str()?.to_string(),
+            cname,
+            reason,
+            date
+        })
+    }
+}
+
+
+pub struct CrlArgs<'a> {
+    pub issuer: &'a X509,
+    pub issuer_key: &'a Key,
+    /// Key identifier of the issuer key, told apart from other keys of the same name
+    pub authority_key_id: &'a [u8],
+    pub crl_number: u64,
+    pub next_update: u32,
+    pub revoked: &'a [Revocation],
+    pub signature: Signature,
+}
+
+
+/// CRLReason codes from RFC 5280
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RevocationReason {
+    #[default]
+    Unspecified,
+    KeyCompromise,
+    CaCompromise,
+    AffiliationChanged,
+    Superseded,
+    CessationOfOperation,
+    CertificateHold,
+    RemoveFromCrl,
+    PrivilegeWithdrawn,
+    AaCompromise,
+}
+
+
+impl RevocationReason {
+    pub fn code(&self) -> u8 {
+        match self {
+            RevocationReason::Unspecified => 0,
+            RevocationReason::KeyCompromise => 1,
+            RevocationReason::CaCompromise => 2,
+            RevocationReason::AffiliationChanged => 3,
+            RevocationReason::Superseded => 4,
+            RevocationReason::CessationOfOperation => 5,
+            RevocationReason::CertificateHold => 6,
+            RevocationReason::RemoveFromCrl => 8,
+            RevocationReason::PrivilegeWithdrawn => 9,
+            RevocationReason::AaCompromise => 10,
+        }
+    }
+}
+
+
+impl FromStr for RevocationReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unspecified" => Ok(RevocationReason::Unspecified),
+            "key-compromise" => Ok(RevocationReason::KeyCompromise),
+            "ca-compromise" => Ok(RevocationReason::CaCompromise),
+            "affiliation-changed" => Ok(RevocationReason::AffiliationChanged),
+            "superseded" => Ok(RevocationReason::Superseded),
+            "cessation-of-operation" => Ok(RevocationReason::CessationOfOperation),
+            "certificate-hold" => Ok(RevocationReason::CertificateHold),
+            "remove-from-crl" => Ok(RevocationReason::RemoveFromCrl),
+            "privilege-withdrawn" => Ok(RevocationReason::PrivilegeWithdrawn),
+            "aa-compromise" => Ok(RevocationReason::AaCompromise),
+            _ => Err(format!("Unknown revocation reason: {}", s))
+        }
+    }
+}
+
+
+impl fmt::Display for RevocationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevocationReason::Unspecified => write!(f, "unspecified"),
+            RevocationReason::KeyCompromise => write!(f, "key-compromise"),
+            RevocationReason::CaCompromise => write!(f, "ca-compromise"),
+            RevocationReason::AffiliationChanged => write!(f, "affiliation-changed"),
+            RevocationReason::Superseded => write!(f, "superseded"),
+            RevocationReason::CessationOfOperation => write!(f, "cessation-of-operation"),
+            RevocationReason::CertificateHold => write!(f, "certificate-hold"),
+            RevocationReason::RemoveFromCrl => write!(f, "remove-from-crl"),
+            RevocationReason::PrivilegeWithdrawn => write!(f, "privilege-withdrawn"),
+            RevocationReason::AaCompromise => write!(f, "aa-compromise"),
+        }
+    }
+}
+
+
+pub fn create_crl(args: CrlArgs) -> Result<X509Crl, ErrorStack> {
+    let last_update = Asn1Time::days_from_now(0)?;
+    let next_update = Asn1Time::days_from_now(args.next_update)?;
+    let crl_number = BigNum::from_dec_str(&args.crl_number.to_string())?.to_asn1_integer()?;
+    let pkey = args.issuer_key.to_private_pkey()?;
+    let authority_key_id = authority_key_identifier(args.authority_key_id)?;
+
+    // The openssl crate cannot build CRLs, drive libcrypto directly.
+    // Every pointer handed to a set1/add1 function is copied by openssl,
+    // and `crl` owns the revoked entries once added.
+    unsafe {
+        let crl = X509Crl::from_ptr(_cvt_p(ffi::X509_CRL_new())?);
+
+        _cvt(ffi::X509_CRL_set_version(crl.as_ptr(), 1))?;
+        _cvt(ffi::X509_CRL_set_issuer_name(crl.as_ptr(), args.issuer.subject_name().as_ptr()))?;
+        _cvt(ffi::X509_CRL_set1_lastUpdate(crl.as_ptr(), last_update.as_ptr()))?;
+        _cvt(ffi::X509_CRL_set1_nextUpdate(crl.as_ptr(), next_update.as_ptr()))?;
+
+        for revocation in args.revoked {
+            let serial = BigNum::from_hex_str(&revocation.serial)?.to_asn1_integer()?;
+            let date = Asn1Time::from_unix(revocation.date as _)?;
+            let reason = _get_reason_extension(revocation.reason)?;
+
+            let revoked = _cvt_p(ffi::X509_REVOKED_new())?;
+            if ffi::X509_CRL_add0_revoked(crl.as_ptr(), revoked) <= 0 {
+                ffi::X509_REVOKED_free(revoked);
+                return Err(ErrorStack::get());
+            }
+
+            _cvt(ffi::X509_REVOKED_set_serialNumber(revoked, serial.as_ptr()))?;
+            _cvt(ffi::X509_REVOKED_set_revocationDate(revoked, date.as_ptr()))?;
+            _cvt(ffi::X509_REVOKED_add_ext(revoked, reason.as_ptr(), -1))?;
+        }
+
+        _cvt(ffi::X509_CRL_add1_ext_i2d(
+            crl.as_ptr(),
+            ffi::NID_crl_number,
+            crl_number.as_ptr() as *mut _,
+            0,
+            0
+        ))?;
+        _cvt(ffi::X509_CRL_add_ext(crl.as_ptr(), authority_key_id.as_ptr(), -1))?;
+
+        _cvt(ffi::X509_CRL_sort(crl.as_ptr()))?;
+        sign_crl(&crl, &pkey, args.signature)?;
+
+        Ok(crl)
+    }
+}
+
+
+// Private
+fn _get_reason_extension(reason: RevocationReason) -> Result<X509Extension, ErrorStack> {
+    // CRLReason ::= ENUMERATED
+    der_extension(CRL_REASON_OID, false, &der::tlv(der::TAG_ENUMERATED, &[reason.code()]))
+}
+
+
+fn _cvt_p<T>(r: *mut T) -> Result<*mut T, ErrorStack> {
+    match r.is_null() {
+        true => Err(ErrorStack::get()),
+        false => Ok(r)
+    }
+}
+
+
+fn _cvt(r: i32) -> Result<i32, ErrorStack> {
+    match r <= 0 {
+        true => Err(ErrorStack::get()),
+        false => Ok(r)
+    }
+}
@@ -0,0 +1,186 @@
+use core::fmt;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr
+};
+
+use openssl::{error::ErrorStack, nid::Nid, x509::X509Req};
+use serde::{Deserialize, Serialize};
+
+use crate::certificates::{
+    der::{self, Der, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE},
+    AltNames,
+    Subject
+};
+
+
+const SAN_OID: &str = "2.5.29.17";
+
+
+/// What a signed CSR may carry over to its certificate
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CsrPolicy {
+    /// Common name only, the other DN attributes come from the configuration
+    CommonName,
+    /// Common name and Subject Alternative Names
+    #[default]
+    AltNames,
+    /// Every DN attribute and Subject Alternative Names
+    Full,
+}
+
+
+/// Names requested by a CSR
+#[derive(Default, Debug, PartialEq)]
+pub struct CsrNames {
+    pub common_name: Option<String>,
+    pub subject: Subject,
+    pub san: AltNames,
+}
+
+
+impl CsrPolicy {
+    /// Keep the requested names this policy allows
+    pub fn apply(&self, mut names: CsrNames) -> CsrNames {
+        match self {
+            CsrPolicy::CommonName => CsrNames { common_name: names.common_name, ..CsrNames::default() },
+            CsrPolicy::AltNames => CsrNames { subject: Subject::default(), ..names },
+            // Attributes the request leaves out are not completed from the configuration
+            CsrPolicy::Full => {
+                let subject = &mut names.subject;
+
+                for attribute in [&mut subject.country, &mut subject.state, &mut subject.organization] {
+                    attribute.get_or_insert_with(String::new);
+                }
+
+                names
+            },
+        }
+    }
+}
+
+
+impl FromStr for CsrPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "common-name" => Ok(CsrPolicy::CommonName),
+            "alt-names" => Ok(CsrPolicy::AltNames),
+            "full" => Ok(CsrPolicy::Full),
+            _ => Err(format!("Unknown CSR policy: {}", s))
+        }
+    }
+}
+
+
+impl fmt::Display for CsrPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsrPolicy::CommonName => write!(f, "common-name"),
+            CsrPolicy::AltNames => write!(f, "alt-names"),
+            CsrPolicy::Full => write!(f, "full"),
+        }
+    }
+}
+
+
+/// Read a PEM or DER encoded PKCS#10 request
+pub fn read_csr(data: &[u8]) -> Result<X509Req, ErrorStack> {
+    X509Req::from_pem(data).or_else(|_| X509Req::from_der(data))
+}
+
+
+/// Subject and Subject Alternative Names requested by a CSR
+pub fn requested_names(req: &X509Req) -> Result<CsrNames, ErrorStack> {
+    let mut names = CsrNames::default();
+
+    for entry in req.subject_name().entries() {
+        let value = entry.data().as_utf8()?.to_string();
+
+        match entry.object().nid() {
+            Nid::COMMONNAME => names.common_name = Some(value),
+            // Attributes unknown to openssl are kept by OID
+            Nid::UNDEF => names.subject.set(&entry.object().to_string(), &value),
+            nid => match nid.short_name() {
+                Ok(field) => names.subject.set(field, &value),
+                Err(_) => names.subject.set(&entry.object().to_string(), &value)
+            }
+        }
+    }
+
+    // Requests without extensions make openssl fail
+    if let Ok(extensions) = req.extensions() {
+        for extension in extensions.iter() {
+            if let Some(san) = _parse_alt_names(&extension.to_der()?) {
+                names.san = san;
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+
+/// Check the Subject Alternative Names a CSR requests: each one must be configured for the
+/// certificate, or a DNS name or email address within one of the `allowed_domains`.
+pub fn check_alt_names(requested: &AltNames, configured: &AltNames, allowed_domains: &[String]) -> Result<(), String> {
+    let allowed = |domain: &str| allowed_domains.iter().any(|allowed| {
+        let (domain, allowed) = (domain.to_lowercase(), allowed.trim_start_matches('.').to_lowercase());
+
+        domain == allowed || domain.ends_with(&format!(".{}", allowed))
+    });
+
+    let refused = requested.dns.iter().find(|dns| !configured.dns.contains(dns) && !allowed(dns))
+        .or_else(|| requested.email.iter().find(|email| {
+            !configured.email.contains(email) && !email.rsplit_once('@').is_some_and(|(_, domain)| allowed(domain))
+        }))
+        .or_else(|| requested.ip.iter().find(|ip| !configured.ip.contains(ip)))
+        .or_else(|| requested.uri.iter().find(|uri| !configured.uri.contains(uri)));
+
+    match refused {
+        Some(name) => Err(format!("CSR requests {} which is neither configured nor in an allowed domain", name)),
+        None => Ok(())
+    }
+}
+
+
+// Private
+/// GeneralNames of a DER encoded subjectAltName extension
+fn _parse_alt_names(extension: &[u8]) -> Option<AltNames> {
+    let mut fields = Der::new(extension).expect(TAG_SEQUENCE)?.children();
+
+    if fields.expect(TAG_OID)?.raw != der::oid(SAN_OID)?.as_slice() {
+        return None;
+    }
+
+    // critical
+    fields.read_optional(0x01);
+
+    let value = fields.expect(TAG_OCTET_STRING)?;
+    let mut general_names = Der::new(value.content).expect(TAG_SEQUENCE)?.children();
+    let mut san = AltNames::default();
+
+    while let Some(name) = general_names.read() {
+        let text = || String::from_utf8_lossy(name.content).to_string();
+
+        match name.tag {
+            0x81 => san.email.push(text()),
+            0x82 => san.dns.push(text()),
+            0x86 => san.uri.push(text()),
+            0x87 => {
+                let ip = match name.content.len() {
+                    4 => IpAddr::from(Ipv4Addr::from(<[u8; 4]>::try_from(name.content).ok()?)),
+                    _ => IpAddr::from(Ipv6Addr::from(<[u8; 16]>::try_from(name.content).ok()?)),
+                };
+
+                san.ip.push(ip.to_string());
+            },
+            // Other name types cannot be copied
+            _ => ()
+        }
+    }
+
+    Some(san)
+}
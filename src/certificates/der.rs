@@ -0,0 +1,196 @@
+//! Minimal DER encoding and decoding, for the structures the openssl crate
+//! cannot build or inspect by itself.
+
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_ENUMERATED: u8 = 0x0a;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_IA5_STRING: u8 = 0x16;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_SEQUENCE: u8 = 0x30;
+
+
+// Encoding
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut der = vec![tag];
+    let len = content.len();
+
+    if len < 0x80 {
+        der.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+
+        der.push(0x80 | bytes.len() as u8);
+        der.extend(bytes);
+    }
+
+    der.extend_from_slice(content);
+    der
+}
+
+
+pub fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    tlv(TAG_SEQUENCE, &items.concat())
+}
+
+
+/// Context specific, constructed tag, used for EXPLICIT tagging
+pub fn explicit(number: u8, content: &[u8]) -> Vec<u8> {
+    tlv(0xa0 | number, content)
+}
+
+
+/// Context specific, primitive tag, used for IMPLICIT tagging of primitive types
+pub fn implicit(number: u8, content: &[u8]) -> Vec<u8> {
+    tlv(0x80 | number, content)
+}
+
+
+/// Content of a non negative INTEGER, in its minimal encoding
+pub fn unsigned(value: u64) -> Vec<u8> {
+    let bytes: Vec<u8> = value.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+
+    match bytes.first() {
+        None => vec![0],
+        Some(first) if first & 0x80 != 0 => [&[0], &bytes[..]].concat(),
+        Some(_) => bytes
+    }
+}
+
+
+pub fn null() -> Vec<u8> {
+    tlv(TAG_NULL, &[])
+}
+
+
+pub fn bit_string(content: &[u8]) -> Vec<u8> {
+    tlv(TAG_BIT_STRING, &[&[0], content].concat())
+}
+
+
+pub fn oid(dotted: &str) -> Option<Vec<u8>> {
+    let arcs = dotted.split('.')
+        .map(|arc| arc.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] > 39) {
+        return None;
+    }
+
+    let mut content = vec![];
+
+    for arc in [arcs[0] * 40 + arcs[1]].iter().chain(&arcs[2..]) {
+        let mut bytes = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+
+        while rest > 0 {
+            bytes.insert(0, 0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+
+        content.extend(bytes);
+    }
+
+    Some(tlv(TAG_OID, &content))
+}
+
+
+/// GeneralizedTime from seconds since the Unix epoch
+pub fn generalized_time(timestamp: i64) -> Vec<u8> {
+    let (days, seconds) = (timestamp.div_euclid(86400), timestamp.rem_euclid(86400));
+
+    // Civil date from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let time = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        year, month, day, seconds / 3600, seconds % 3600 / 60, seconds % 60
+    );
+
+    tlv(TAG_GENERALIZED_TIME, time.as_bytes())
+}
+
+
+// Decoding
+/// Reader over consecutive DER elements
+pub struct Der<'a> {
+    data: &'a [u8],
+}
+
+
+/// A decoded DER element
+#[derive(Clone, Copy)]
+pub struct Element<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    pub raw: &'a [u8],
+}
+
+
+impl<'a> Der<'a> {
+    pub fn new(data: &'a [u8]) -> Der<'a> {
+        Der { data }
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+
+    pub fn read(&mut self) -> Option<Element<'a>> {
+        let (&tag, rest) = self.data.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+
+        let len = match first {
+            0..=0x7f => first as usize,
+            0x81..=0x84 => {
+                let (bytes, remaining) = rest.split_at_checked((first & 0x7f) as usize)?;
+                rest = remaining;
+
+                bytes.iter().fold(0usize, |len, byte| (len << 8) | *byte as usize)
+            },
+            _ => return None
+        };
+
+        let content = rest.get(..len)?;
+        let header = self.data.len() - rest.len();
+        let raw = &self.data[..header + len];
+
+        self.data = &rest[len..];
+
+        Some(Element { tag, content, raw })
+    }
+
+
+    /// Read the next element if it has the given tag
+    pub fn read_optional(&mut self, tag: u8) -> Option<Element<'a>> {
+        match self.data.first() {
+            Some(first) if *first == tag => self.read(),
+            _ => None
+        }
+    }
+
+
+    pub fn expect(&mut self, tag: u8) -> Option<Element<'a>> {
+        self.read().filter(|element| element.tag == tag)
+    }
+}
+
+
+impl<'a> Element<'a> {
+    pub fn children(&self) -> Der<'a> {
+        Der::new(self.content)
+    }
+}
@@ -0,0 +1,94 @@
+//! Extensions given in the specification of a certificate, added as they are. Their value
+//! is raw DER, a string, or an openssl configuration value such as `DER:...` or `ASN1:...`.
+use openssl::{asn1::Asn1Object, base64, error::ErrorStack, x509::X509Extension};
+use serde::{Deserialize, Serialize};
+
+use crate::certificates::{der, x509::der_extension};
+
+
+/// Extensions pkimgr writes itself, a second occurrence would make certificates invalid
+const MANAGED_OIDS: [&str; 13] = [
+    "2.5.29.14", // subjectKeyIdentifier
+    "2.5.29.15", // keyUsage
+    "2.5.29.17", // subjectAltName
+    "2.5.29.19", // basicConstraints
+    "2.5.29.30", // nameConstraints
+    "2.5.29.31", // cRLDistributionPoints
+    "2.5.29.32", // certificatePolicies
+    "2.5.29.35", // authorityKeyIdentifier
+    "2.5.29.36", // policyConstraints
+    "2.5.29.37", // extKeyUsage
+    "2.5.29.54", // inhibitAnyPolicy
+    "1.3.6.1.5.5.7.1.1", // authorityInfoAccess
+    "1.3.6.1.5.5.7.48.1.5", // id-pkix-ocsp-nocheck, set by the ocsp-signing profile
+];
+
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct CustomExtension {
+    /// Dotted identifier of the extension
+    pub oid: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub critical: bool,
+    /// Base64 DER value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_der_base64: Option<String>,
+    /// Value encoded as an UTF8String
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_string: Option<String>,
+    /// Value in the openssl configuration syntax, `ASN1:NULL` or `DER:05:00` for example
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conf: Option<String>,
+}
+
+
+impl CustomExtension {
+    pub fn validate(&self) -> Result<(), String> {
+        let oid = der::oid(&self.oid).ok_or_else(|| format!("invalid OID {:?}", self.oid))?;
+
+        // Written with the tag and length of the OID, compared on its content
+        if MANAGED_OIDS.iter().any(|managed| der::oid(managed).is_some_and(|managed| managed == oid)) {
+            return Err(format!("{} is set by pkimgr from the specification", self.oid));
+        }
+
+        let values = [self.value_der_base64.is_some(), self.value_string.is_some(), self.conf.is_some()];
+
+        if values.iter().filter(|set| **set).count() != 1 {
+            return Err(format!("{}: one of value_der_base64, value_string or conf is required", self.oid));
+        }
+
+        if let Some(value) = &self.value_der_base64 {
+            let value = base64::decode_block(value).map_err(|_| format!("{}: value_der_base64 is not base64", self.oid))?;
+            let mut elements = der::Der::new(&value);
+
+            if elements.read().is_none() || !elements.is_empty() {
+                return Err(format!("{}: value_der_base64 is not a single DER element", self.oid));
+            }
+        }
+
+        self.to_extension().map(|_| ()).map_err(|err| format!("{}: {}", self.oid, err))
+    }
+
+
+    pub fn to_extension(&self) -> Result<X509Extension, ErrorStack> {
+        if let Some(value) = &self.value_der_base64 {
+            return der_extension(&self.oid, self.critical, &base64::decode_block(value)?);
+        }
+
+        if let Some(value) = &self.value_string {
+            return der_extension(&self.oid, self.critical, &der::tlv(der::TAG_UTF8_STRING, value.as_bytes()));
+        }
+
+        let conf = self.conf.as_deref().unwrap_or_default();
+        let value = match self.critical {
+            true => format!("critical,{}", conf),
+            false => conf.to_string()
+        };
+        // The OID is checked first, openssl would read a name as an extension short name
+        let oid = Asn1Object::from_str(&self.oid)?;
+
+        // No replacement builds an extension of any OID from a configuration value
+        #[allow(deprecated)]
+        X509Extension::new(None, None, &oid.to_string(), &value)
+    }
+}
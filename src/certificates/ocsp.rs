@@ -0,0 +1,214 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use openssl::{
+    bn::BigNum,
+    error::ErrorStack,
+    hash::{hash, MessageDigest},
+    x509::X509
+};
+
+use crate::{
+    certificates::{
+        crl::RevocationReason,
+        der::{self, Der, TAG_ENUMERATED, TAG_INTEGER, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE},
+        signature::{sign, Signature},
+        KeyIdentifier
+    },
+    key::Key
+};
+
+
+const BASIC_RESPONSE_OID: &str = "1.3.6.1.5.5.7.48.1.1";
+const NONCE_OID: &str = "1.3.6.1.5.5.7.48.1.2";
+pub const NO_CHECK_OID: &str = "1.3.6.1.5.5.7.48.1.5";
+
+
+/// OCSPResponseStatus values from RFC 6960
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseStatus {
+    Successful = 0,
+    MalformedRequest = 1,
+    InternalError = 2,
+    Unauthorized = 6,
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CertStatus {
+    Good,
+    Revoked { date: i64, reason: RevocationReason },
+    Unknown,
+}
+
+
+/// Certificate identifier of an OCSP request
+pub struct CertId<'a> {
+    pub raw: &'a [u8],
+    pub digest: MessageDigest,
+    pub issuer_name_hash: &'a [u8],
+    pub issuer_key_hash: &'a [u8],
+    pub serial: BigNum,
+}
+
+
+pub struct OcspRequest<'a> {
+    pub ids: Vec<CertId<'a>>,
+    /// Nonce extension, echoed back in the response
+    pub nonce: Option<&'a [u8]>,
+}
+
+
+pub struct OcspArgs<'a> {
+    pub responder: &'a X509,
+    pub responder_key: &'a Key,
+    /// Responder certificate to embed, for delegated responders
+    pub delegated: bool,
+    pub next_update: u32,
+    pub nonce: Option<&'a [u8]>,
+    pub responses: Vec<(&'a CertId<'a>, CertStatus)>,
+    pub signature: Signature,
+}
+
+
+impl CertId<'_> {
+    /// Whether this identifier designates a certificate issued by `issuer`
+    pub fn is_issued_by(&self, issuer: &X509) -> Result<bool, ErrorStack> {
+        let name_hash = hash(self.digest, &issuer.subject_name().to_der()?)?;
+        let public_key = issuer.public_key()?;
+        let key_hash = hash(self.digest, &KeyIdentifier::public_key_bits(&public_key)?)?;
+
+        Ok(*name_hash == *self.issuer_name_hash && *key_hash == *self.issuer_key_hash)
+    }
+}
+
+
+/// Parse a DER encoded OCSP request. Request signatures are ignored.
+pub fn parse_request(request: &[u8]) -> Option<OcspRequest<'_>> {
+    let mut ocsp_request = Der::new(request).expect(TAG_SEQUENCE)?.children();
+    let mut tbs_request = ocsp_request.expect(TAG_SEQUENCE)?.children();
+
+    // version and requestorName
+    tbs_request.read_optional(0xa0);
+    tbs_request.read_optional(0xa1);
+
+    let mut request_list = tbs_request.expect(TAG_SEQUENCE)?.children();
+    let mut ids = vec![];
+
+    while !request_list.is_empty() {
+        let mut single_request = request_list.expect(TAG_SEQUENCE)?.children();
+
+        ids.push(_parse_cert_id(single_request.expect(TAG_SEQUENCE)?)?);
+    }
+
+    let nonce_oid = der::oid(NONCE_OID)?;
+    let mut nonce = None;
+
+    if let Some(extensions) = tbs_request.read_optional(0xa2) {
+        let mut extensions = extensions.children().expect(TAG_SEQUENCE)?.children();
+
+        while let Some(extension) = extensions.read() {
+            if extension.children().expect(TAG_OID)?.raw == nonce_oid.as_slice() {
+                nonce = Some(extension.raw);
+            }
+        }
+    }
+
+    Some(OcspRequest { ids, nonce })
+}
+
+
+/// Build and sign a successful, DER encoded, OCSP response
+pub fn create_response(args: OcspArgs) -> Result<Vec<u8>, ErrorStack> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    let next_update = now + i64::from(args.next_update) * 86400;
+
+    let responses: Vec<Vec<u8>> = args.responses.iter()
+        .map(|(id, status)| der::sequence(&[
+            id.raw.to_vec(),
+            _get_cert_status(status),
+            der::generalized_time(now),
+            der::explicit(0, &der::generalized_time(next_update))
+        ]))
+        .collect();
+
+    let mut response_data = vec![
+        der::explicit(1, &args.responder.subject_name().to_der()?),
+        der::generalized_time(now),
+        der::sequence(&responses),
+    ];
+
+    if let Some(nonce) = args.nonce {
+        response_data.push(der::explicit(1, &der::sequence(&[nonce.to_vec()])));
+    }
+
+    let tbs_response_data = der::sequence(&response_data);
+
+    let pkey = args.responder_key.to_private_pkey()?;
+    let signature = sign(&tbs_response_data, &pkey, args.signature)?;
+
+    let mut basic_response = vec![
+        tbs_response_data,
+        args.signature.algorithm_identifier(args.responder_key),
+        der::bit_string(&signature),
+    ];
+
+    if args.delegated {
+        basic_response.push(der::explicit(0, &der::sequence(&[args.responder.to_der()?])));
+    }
+
+    let response_bytes = der::sequence(&[
+        der::oid(BASIC_RESPONSE_OID).unwrap_or_default(),
+        der::tlv(TAG_OCTET_STRING, &der::sequence(&basic_response)),
+    ]);
+
+    Ok(der::sequence(&[
+        der::tlv(TAG_ENUMERATED, &[ResponseStatus::Successful as u8]),
+        der::explicit(0, &response_bytes),
+    ]))
+}
+
+
+/// Unsigned OCSP response carrying an error status
+pub fn error_response(status: ResponseStatus) -> Vec<u8> {
+    der::sequence(&[der::tlv(TAG_ENUMERATED, &[status as u8])])
+}
+
+
+// Private
+fn _parse_cert_id(cert_id: der::Element<'_>) -> Option<CertId<'_>> {
+    let mut fields = cert_id.children();
+    let algorithm = fields.expect(TAG_SEQUENCE)?.children().expect(TAG_OID)?;
+
+    let digest = [
+        ("1.3.14.3.2.26", MessageDigest::sha1()),
+        ("2.16.840.1.101.3.4.2.1", MessageDigest::sha256()),
+        ("2.16.840.1.101.3.4.2.2", MessageDigest::sha384()),
+        ("2.16.840.1.101.3.4.2.3", MessageDigest::sha512()),
+    ]
+        .into_iter()
+        .find(|(oid, _)| der::oid(oid).is_some_and(|oid| oid == algorithm.raw))?
+        .1;
+
+    Some(CertId {
+        raw: cert_id.raw,
+        digest,
+        issuer_name_hash: fields.expect(TAG_OCTET_STRING)?.content,
+        issuer_key_hash: fields.expect(TAG_OCTET_STRING)?.content,
+        serial: BigNum::from_slice(fields.expect(TAG_INTEGER)?.content).ok()?,
+    })
+}
+
+
+fn _get_cert_status(status: &CertStatus) -> Vec<u8> {
+    match status {
+        CertStatus::Good => der::implicit(0, &[]),
+        CertStatus::Revoked { date, reason } => der::tlv(0xa1, &[
+            der::generalized_time(*date),
+            der::explicit(0, &der::tlv(TAG_ENUMERATED, &[reason.code()])),
+        ].concat()),
+        CertStatus::Unknown => der::implicit(2, &[]),
+    }
+}
@@ -0,0 +1,131 @@
+//! Certificate policies asserted by a certificate, and the constraints an authority puts on
+//! the policies of the paths going through it.
+use openssl::{error::ErrorStack, x509::X509Extension};
+use serde::{Deserialize, Serialize};
+
+use crate::certificates::{der, x509::der_extension};
+
+
+const CERTIFICATE_POLICIES_OID: &str = "2.5.29.32";
+const POLICY_CONSTRAINTS_OID: &str = "2.5.29.36";
+const INHIBIT_ANY_POLICY_OID: &str = "2.5.29.54";
+const CPS_QUALIFIER_OID: &str = "1.3.6.1.5.5.7.2.1";
+const USER_NOTICE_QUALIFIER_OID: &str = "1.3.6.1.5.5.7.2.2";
+
+/// Longest explicit text of a user notice, RFC 5280 section 4.2.1.4
+const NOTICE_MAX_LEN: usize = 200;
+
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct Policy {
+    /// Dotted policy identifier, `2.5.29.32.0` for anyPolicy
+    pub oid: String,
+    /// URIs of the Certification Practice Statement
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cps: Vec<String>,
+    /// Explicit text of a user notice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notice: Option<String>,
+}
+
+
+/// Number of certificates below an authority before policies become required, or
+/// policy mappings are refused
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct PolicyConstraints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_explicit_policy: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inhibit_policy_mapping: Option<u32>,
+}
+
+
+impl Policy {
+    pub fn validate(&self) -> Result<(), String> {
+        der::oid(&self.oid).ok_or_else(|| format!("invalid policy identifier {:?}", self.oid))?;
+
+        for cps in self.cps.iter() {
+            if !cps.contains("://") || !cps.is_ascii() || cps.contains(char::is_whitespace) {
+                return Err(format!("{}: CPS {:?} is not an URI", self.oid, cps));
+            }
+        }
+
+        if self.notice.as_ref().is_some_and(|notice| notice.chars().count() > NOTICE_MAX_LEN) {
+            return Err(format!("{}: notice is longer than {} characters", self.oid, NOTICE_MAX_LEN));
+        }
+
+        Ok(())
+    }
+
+
+    /// DER PolicyInformation
+    fn to_der(&self) -> Result<Vec<u8>, ErrorStack> {
+        let oid = |dotted: &str| der::oid(dotted).ok_or_else(ErrorStack::get);
+        let mut qualifiers = vec![];
+
+        for cps in self.cps.iter() {
+            qualifiers.push(der::sequence(&[oid(CPS_QUALIFIER_OID)?, der::tlv(der::TAG_IA5_STRING, cps.as_bytes())]));
+        }
+
+        if let Some(notice) = &self.notice {
+            let user_notice = der::sequence(&[der::tlv(der::TAG_UTF8_STRING, notice.as_bytes())]);
+
+            qualifiers.push(der::sequence(&[oid(USER_NOTICE_QUALIFIER_OID)?, user_notice]));
+        }
+
+        let mut information = vec![oid(&self.oid)?];
+
+        if !qualifiers.is_empty() {
+            information.push(der::sequence(&qualifiers));
+        }
+
+        Ok(der::sequence(&information))
+    }
+}
+
+
+impl PolicyConstraints {
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.require_explicit_policy, self.inhibit_policy_mapping) {
+            (None, None) => Err("require_explicit_policy or inhibit_policy_mapping is required".to_string()),
+            _ => Ok(())
+        }
+    }
+}
+
+
+/// Certificate policies, along with the policy constraints of an authority. Both
+/// constraints are critical, as RFC 5280 requires.
+pub fn policy_extensions(
+    policies: &[Policy],
+    constraints: Option<&PolicyConstraints>,
+    inhibit_any_policy: Option<u32>
+) -> Result<Vec<X509Extension>, ErrorStack> {
+    let mut extensions = vec![];
+
+    if !policies.is_empty() {
+        let information = policies.iter().map(Policy::to_der).collect::<Result<Vec<Vec<u8>>, ErrorStack>>()?;
+
+        extensions.push(der_extension(CERTIFICATE_POLICIES_OID, false, &der::sequence(&information))?);
+    }
+
+    if let Some(constraints) = constraints {
+        let mut value = vec![];
+
+        if let Some(skip_certs) = constraints.require_explicit_policy {
+            value.push(der::implicit(0, &der::unsigned(skip_certs.into())));
+        }
+
+        if let Some(skip_certs) = constraints.inhibit_policy_mapping {
+            value.push(der::implicit(1, &der::unsigned(skip_certs.into())));
+        }
+
+        extensions.push(der_extension(POLICY_CONSTRAINTS_OID, true, &der::sequence(&value))?);
+    }
+
+    if let Some(skip_certs) = inhibit_any_policy {
+        extensions.push(der_extension(INHIBIT_ANY_POLICY_OID, true, &der::tlv(der::TAG_INTEGER, &der::unsigned(skip_certs.into())))?);
+    }
+
+    Ok(extensions)
+}
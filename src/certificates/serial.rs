@@ -0,0 +1,95 @@
+//! Serial numbers of the certificates issued by an authority, drawn at random or from a
+//! counter, and the index of the ones already used.
+use std::collections::BTreeSet;
+
+use openssl::{
+    bn::{BigNum, MsbOption},
+    error::ErrorStack
+};
+use serde::{Deserialize, Serialize};
+
+
+/// Longest serial number, so that its positive DER INTEGER fits in the 20 octets of RFC 5280
+const SERIAL_BITS: i32 = 159;
+
+
+/// How an authority draws the serial numbers of the certificates it issues
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SerialStrategy {
+    /// 159 bits, 158 of them random
+    #[default]
+    Random,
+    /// 1, 2, 3... counted per authority
+    Sequential,
+}
+
+
+/// Serial numbers used by an authority
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct SerialIndex {
+    /// Last sequential serial number
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub counter: u64,
+    /// Hexadecimal serial numbers
+    #[serde(default)]
+    pub issued: BTreeSet<String>,
+}
+
+
+impl SerialIndex {
+    /// Serial number never used by this authority. It is only reserved, [`SerialIndex::record`]
+    /// takes it once the certificate is issued.
+    pub fn next(&self, strategy: SerialStrategy) -> Result<BigNum, ErrorStack> {
+        let mut counter = self.counter;
+
+        loop {
+            let serial = match strategy {
+                SerialStrategy::Random => random()?,
+                SerialStrategy::Sequential => {
+                    counter += 1;
+                    BigNum::from_slice(&counter.to_be_bytes())?
+                }
+            };
+
+            if !self.issued.contains(&serial.to_hex_str()?.to_string()) {
+                return Ok(serial);
+            }
+        }
+    }
+
+
+    /// Record a serial number, `false` when it is already used
+    pub fn record(&mut self, serial: &BigNum) -> Result<bool, ErrorStack> {
+        // Random serial numbers are too long for the counter to follow them
+        if serial.num_bits() <= 64 {
+            let value = serial.to_vec().iter().fold(0u64, |value, byte| (value << 8) | u64::from(*byte));
+
+            self.counter = self.counter.max(value);
+        }
+
+        Ok(self.issued.insert(serial.to_hex_str()?.to_string()))
+    }
+
+
+    /// Add the serial numbers of another index of the same authority
+    pub fn merge(&mut self, other: SerialIndex) {
+        self.counter = self.counter.max(other.counter);
+        self.issued.extend(other.issued);
+    }
+}
+
+
+/// Random positive serial number of [`SERIAL_BITS`] bits, its most significant one set
+pub fn random() -> Result<BigNum, ErrorStack> {
+    let mut serial = BigNum::new()?;
+
+    serial.rand(SERIAL_BITS, MsbOption::ONE, false)?;
+
+    Ok(serial)
+}
+
+
+fn is_zero(counter: &u64) -> bool {
+    *counter == 0
+}
@@ -0,0 +1,249 @@
+//! Signing with a configurable digest and RSA padding. The openssl crate only signs
+//! certificates and requests with PKCS#1 v1.5, so libcrypto is driven through a `MdCtx`.
+use core::fmt;
+use std::{ffi::c_int, str::FromStr};
+
+use foreign_types::ForeignType;
+use openssl::{
+    error::ErrorStack,
+    hash::MessageDigest,
+    md::Md,
+    md_ctx::MdCtx,
+    pkey::{Id, PKey, Private},
+    rsa::Padding,
+    sign::RsaPssSaltlen,
+    x509::{X509Crl, X509Req, X509}
+};
+use openssl_sys as ffi;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    certificates::der,
+    key::{Curve, Key}
+};
+
+
+const RSA_PSS_OID: &str = "1.2.840.113549.1.1.10";
+const MGF1_OID: &str = "1.2.840.113549.1.1.8";
+const ED25519_OID: &str = "1.3.101.112";
+const ED448_OID: &str = "1.3.101.113";
+
+
+// Not exposed by openssl-sys
+extern "C" {
+    fn X509_sign_ctx(x: *mut ffi::X509, ctx: *mut ffi::EVP_MD_CTX) -> c_int;
+    fn X509_REQ_sign_ctx(req: *mut ffi::X509_REQ, ctx: *mut ffi::EVP_MD_CTX) -> c_int;
+    fn X509_CRL_sign_ctx(crl: *mut ffi::X509_CRL, ctx: *mut ffi::EVP_MD_CTX) -> c_int;
+}
+
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Digest {
+    Sha256,
+    Sha384,
+    Sha512,
+    #[serde(rename = "sha3-256")]
+    Sha3_256,
+    #[serde(rename = "sha3-384")]
+    Sha3_384,
+    #[serde(rename = "sha3-512")]
+    Sha3_512,
+}
+
+
+/// Digest and padding used to sign
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signature {
+    /// Ignored for EdDSA keys, which hash the data themselves
+    pub digest: Digest,
+    /// RSASSA-PSS instead of PKCS#1 v1.5, ignored for non RSA keys
+    pub rsa_pss: bool,
+}
+
+
+impl Digest {
+    pub fn message_digest(&self) -> MessageDigest {
+        match self {
+            Digest::Sha256 => MessageDigest::sha256(),
+            Digest::Sha384 => MessageDigest::sha384(),
+            Digest::Sha512 => MessageDigest::sha512(),
+            Digest::Sha3_256 => MessageDigest::sha3_256(),
+            Digest::Sha3_384 => MessageDigest::sha3_384(),
+            Digest::Sha3_512 => MessageDigest::sha3_512(),
+        }
+    }
+
+
+    fn oid(&self) -> &'static str {
+        match self {
+            Digest::Sha256 => "2.16.840.1.101.3.4.2.1",
+            Digest::Sha384 => "2.16.840.1.101.3.4.2.2",
+            Digest::Sha512 => "2.16.840.1.101.3.4.2.3",
+            Digest::Sha3_256 => "2.16.840.1.101.3.4.2.8",
+            Digest::Sha3_384 => "2.16.840.1.101.3.4.2.9",
+            Digest::Sha3_512 => "2.16.840.1.101.3.4.2.10",
+        }
+    }
+}
+
+
+impl FromStr for Digest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" => Ok(Digest::Sha256),
+            "sha384" => Ok(Digest::Sha384),
+            "sha512" => Ok(Digest::Sha512),
+            "sha3-256" => Ok(Digest::Sha3_256),
+            "sha3-384" => Ok(Digest::Sha3_384),
+            "sha3-512" => Ok(Digest::Sha3_512),
+            _ => Err(format!("Unknown digest: {}", s))
+        }
+    }
+}
+
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Digest::Sha256 => write!(f, "sha256"),
+            Digest::Sha384 => write!(f, "sha384"),
+            Digest::Sha512 => write!(f, "sha512"),
+            Digest::Sha3_256 => write!(f, "sha3-256"),
+            Digest::Sha3_384 => write!(f, "sha3-384"),
+            Digest::Sha3_512 => write!(f, "sha3-512"),
+        }
+    }
+}
+
+
+impl Signature {
+    /// Default signature of a key, with a digest matching its strength
+    pub fn for_key(key: &Key) -> Signature {
+        let digest = match key.curve().and_then(|curve| curve.parse::<Curve>().ok()) {
+            Some(Curve::Secp384r1) => Digest::Sha384,
+            Some(Curve::Secp521r1) => Digest::Sha512,
+            _ => Digest::Sha256
+        };
+
+        Signature { digest, rsa_pss: false }
+    }
+
+
+    /// DER AlgorithmIdentifier of this signature made with `key`
+    pub fn algorithm_identifier(&self, key: &Key) -> Vec<u8> {
+        let oid = |oid: &str| der::oid(oid).unwrap_or_default();
+        let digest_algorithm = der::sequence(&[oid(self.digest.oid()), der::null()]);
+
+        match key {
+            Key::Rsa(_) if self.rsa_pss => der::sequence(&[
+                oid(RSA_PSS_OID),
+                der::sequence(&[
+                    der::explicit(0, &digest_algorithm),
+                    der::explicit(1, &der::sequence(&[oid(MGF1_OID), digest_algorithm.clone()])),
+                    der::explicit(2, &der::tlv(der::TAG_INTEGER, &[self.digest.message_digest().size() as u8])),
+                ])
+            ]),
+            Key::Rsa(_) => der::sequence(&[oid(_rsa_oid(self.digest)), der::null()]),
+            Key::Ec(_) => der::sequence(&[oid(_ecdsa_oid(self.digest))]),
+            Key::Ed25519(_) => der::sequence(&[oid(ED25519_OID)]),
+            Key::Ed448(_) => der::sequence(&[oid(ED448_OID)]),
+        }
+    }
+}
+
+
+pub fn sign_x509(cert: &X509, pkey: &PKey<Private>, signature: Signature) -> Result<(), ErrorStack> {
+    let ctx = _get_context(pkey, signature)?;
+
+    // The certificate is not shared yet
+    _cvt(unsafe { X509_sign_ctx(cert.as_ptr(), ctx.as_ptr()) })?;
+
+    Ok(())
+}
+
+
+pub fn sign_req(req: &X509Req, pkey: &PKey<Private>, signature: Signature) -> Result<(), ErrorStack> {
+    let ctx = _get_context(pkey, signature)?;
+
+    _cvt(unsafe { X509_REQ_sign_ctx(req.as_ptr(), ctx.as_ptr()) })?;
+
+    Ok(())
+}
+
+
+pub fn sign_crl(crl: &X509Crl, pkey: &PKey<Private>, signature: Signature) -> Result<(), ErrorStack> {
+    let ctx = _get_context(pkey, signature)?;
+
+    _cvt(unsafe { X509_CRL_sign_ctx(crl.as_ptr(), ctx.as_ptr()) })?;
+
+    Ok(())
+}
+
+
+pub fn sign(data: &[u8], pkey: &PKey<Private>, signature: Signature) -> Result<Vec<u8>, ErrorStack> {
+    let mut ctx = _get_context(pkey, signature)?;
+    let mut signed = vec![];
+
+    ctx.digest_sign_to_vec(data, &mut signed)?;
+
+    Ok(signed)
+}
+
+
+// Private
+fn _get_context(pkey: &PKey<Private>, signature: Signature) -> Result<MdCtx, ErrorStack> {
+    let mut ctx = MdCtx::new()?;
+    let md = Md::from_nid(signature.digest.message_digest().type_()).ok_or_else(ErrorStack::get)?;
+
+    // EdDSA signs without a separate digest
+    if matches!(pkey.id(), Id::ED25519 | Id::ED448) {
+        ctx.digest_sign_init(None, pkey)?;
+
+        return Ok(ctx);
+    }
+
+    let pkey_ctx = ctx.digest_sign_init(Some(md), pkey)?;
+
+    if signature.rsa_pss && pkey.id() == Id::RSA {
+        pkey_ctx.set_rsa_padding(Padding::PKCS1_PSS)?;
+        pkey_ctx.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+        pkey_ctx.set_rsa_mgf1_md(md)?;
+    }
+
+    Ok(ctx)
+}
+
+
+fn _rsa_oid(digest: Digest) -> &'static str {
+    match digest {
+        Digest::Sha256 => "1.2.840.113549.1.1.11",
+        Digest::Sha384 => "1.2.840.113549.1.1.12",
+        Digest::Sha512 => "1.2.840.113549.1.1.13",
+        Digest::Sha3_256 => "2.16.840.1.101.3.4.3.14",
+        Digest::Sha3_384 => "2.16.840.1.101.3.4.3.15",
+        Digest::Sha3_512 => "2.16.840.1.101.3.4.3.16",
+    }
+}
+
+
+fn _ecdsa_oid(digest: Digest) -> &'static str {
+    match digest {
+        Digest::Sha256 => "1.2.840.10045.4.3.2",
+        Digest::Sha384 => "1.2.840.10045.4.3.3",
+        Digest::Sha512 => "1.2.840.10045.4.3.4",
+        Digest::Sha3_256 => "2.16.840.1.101.3.4.3.10",
+        Digest::Sha3_384 => "2.16.840.1.101.3.4.3.11",
+        Digest::Sha3_512 => "2.16.840.1.101.3.4.3.12",
+    }
+}
+
+
+fn _cvt(r: c_int) -> Result<c_int, ErrorStack> {
+    match r <= 0 {
+        true => Err(ErrorStack::get()),
+        false => Ok(r)
+    }
+}
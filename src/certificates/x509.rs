@@ -1,12 +1,13 @@
 use openssl::{
-    asn1::{ Asn1Integer, Asn1Time },
-    bn::{ BigNum, MsbOption },
+    asn1::{ Asn1Integer, Asn1Object, Asn1OctetString, Asn1Time },
+    bn::BigNum,
     error::ErrorStack,
-    hash::MessageDigest,
-    pkey::{ PKey, Private, Public },
+    pkey::{ Id, PKey, Private, Public },
+    stack::Stack,
     x509::{
-        extension::{BasicConstraints, KeyUsage},
+        extension::{BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName},
         X509Builder,
+        X509Extension,
         X509Name,
         X509NameBuilder,
         X509Req,
@@ -15,22 +16,41 @@ use openssl::{
     }
 };
 
+use std::{
+    collections::BTreeMap,
+    ffi::{c_int, CString},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr}
+};
+
+use foreign_types::ForeignType;
+use openssl_sys as ffi;
+
 use crate::{
-    certificates::{CertArgs, Certificate, X509Info},
+    certificates::{
+        der,
+        ocsp::NO_CHECK_OID,
+        policies::policy_extensions,
+        signature::{sign_req, sign_x509},
+        AltNames, CertArgs,
+        Certificate,
+        Profile,
+        Subject
+    },
+    error::PKIError,
     key::Key
 };
 
 
+const SUBJECT_KEY_ID_OID: &str = "2.5.29.14";
+const AUTHORITY_KEY_ID_OID: &str = "2.5.29.35";
+
+
 pub fn create_x509_node(args: CertArgs) -> Result<X509, ErrorStack> {
     // name
-    let mut name_builder: X509NameBuilder = _get_name_builder(&args.cert_entries)?;
-    name_builder.append_entry_by_text("CN", &args.name)?;
-
-    let name: X509Name = name_builder.build();
-    let public_key: PKey<Public> = args.key.to_public_pkey()?;
+    let name: X509Name = _get_name(&args.subject, &args.name)?;
 
     // builder
-    let mut cert_builder: X509Builder  =  _get_x509_builder(&args.cert_entries, &public_key)?;
+    let mut cert_builder: X509Builder  =  _get_x509_builder(&args.serial, &args.not_before, &args.not_after, &args.public_key)?;
 
     if args.authority_issuer.is_none() {
         cert_builder.set_issuer_name(&name)?;
@@ -42,11 +62,30 @@ pub fn create_x509_node(args: CertArgs) -> Result<X509, ErrorStack> {
 
     cert_builder.set_subject_name(&name)?;
 
+    if let Some(san) = &args.san {
+        _append_alt_names(&mut cert_builder, san)?;
+    }
+
+    _append_key_identifiers(&mut cert_builder, &args.subject_key_id, args.authority_key_id.as_deref())?;
+
+    for extension in args.access_urls.to_extensions()? {
+        cert_builder.append_extension(extension)?;
+    }
+
     // constraints on extensions
-    let mut bc: BasicConstraints = BasicConstraints::new();
-    cert_builder.append_extension(
-        bc.critical().ca().build().unwrap()
-    )?;
+    cert_builder.append_extension(_get_basic_constraints(args.path_len)?)?;
+
+    if let Some(name_constraints) = &args.name_constraints {
+        cert_builder.append_extension(name_constraints.to_extension()?)?;
+    }
+
+    for extension in policy_extensions(&args.policies, args.policy_constraints.as_ref(), args.inhibit_any_policy)? {
+        cert_builder.append_extension(extension)?;
+    }
+
+    for extension in args.extensions.iter() {
+        cert_builder.append_extension(extension.to_extension()?)?;
+    }
 
     cert_builder.append_extension(
         KeyUsage::new()
@@ -56,100 +95,378 @@ pub fn create_x509_node(args: CertArgs) -> Result<X509, ErrorStack> {
             .build()?
     )?;
 
-    let private_key: PKey<Private> = match args.authority_pkey {
-        Some(pkey) => pkey.to_private_pkey()?,
-        None => args.key.to_private_pkey()?
-    };
-    cert_builder.sign(&private_key, MessageDigest::sha3_256())?;
+    let private_key: PKey<Private> = args.authority_pkey.to_private_pkey()?;
+    let cert = cert_builder.build();
+    sign_x509(&cert, &private_key, args.signature)?;
 
-    Ok(cert_builder.build())
+    Ok(cert)
 }
 
 
 pub fn create_x509_leaf(args: CertArgs) -> Result<X509, ErrorStack> {
-    // Name builder
-    let mut name_builder = _get_name_builder(&args.cert_entries)?;
-    name_builder.append_entry_by_text("CN", &args.name)?;
-
-    // Create CSR
-    let key: PKey<Private> = args.key.to_private_pkey()?;
-    let req: X509Req = _get_x509_req(&key, name_builder)?;
+    // Name
+    let name: X509Name = _get_name(&args.subject, &args.name)?;
 
     // Authority key
     let cert_authority: &X509Name = &args.authority_issuer.ok_or(ErrorStack::get())?;
-    let ca_pkey: PKey<Private> = args.authority_pkey
-        .ok_or(ErrorStack::get())
-        .and_then(|pkey| pkey.to_private_pkey())?
-    ;
+    let ca_pkey: PKey<Private> = args.authority_pkey.to_private_pkey()?;
 
     // cert
-    let public_key: PKey<Public> = args.key.to_public_pkey()?;
-    let mut cert_builder: X509Builder = _get_x509_builder(&args.cert_entries, &public_key)?;
-    cert_builder.set_subject_name(&req.subject_name())?;
+    let mut cert_builder: X509Builder = _get_x509_builder(&args.serial, &args.not_before, &args.not_after, &args.public_key)?;
+    cert_builder.set_subject_name(&name)?;
     cert_builder.set_issuer_name(cert_authority)?;
 
-    cert_builder.sign(&ca_pkey, MessageDigest::sha3_256())?;
+    if let Some(san) = &args.san {
+        _append_alt_names(&mut cert_builder, san)?;
+    }
+
+    _append_key_identifiers(&mut cert_builder, &args.subject_key_id, args.authority_key_id.as_deref())?;
+
+    for extension in args.access_urls.to_extensions()? {
+        cert_builder.append_extension(extension)?;
+    }
+
+    for extension in _get_profile_extensions(args.profile, &args.public_key)? {
+        cert_builder.append_extension(extension)?;
+    }
+
+    for extension in policy_extensions(&args.policies, None, None)? {
+        cert_builder.append_extension(extension)?;
+    }
+
+    for extension in args.extensions.iter() {
+        cert_builder.append_extension(extension.to_extension()?)?;
+    }
 
-    Ok(cert_builder.build())
+    let cert = cert_builder.build();
+    sign_x509(&cert, &ca_pkey, args.signature)?;
+
+    Ok(cert)
 }
 
 
-pub fn x509_to_certificate(cert: &X509, key: &Key) -> Certificate {
-    Certificate {
-        cname: cert.subject_name().entries_by_nid(openssl::nid::Nid::COMMONNAME)
-            .next()
-            .map(|entry| entry.data().as_utf8().unwrap().to_string())
-            .unwrap_or_default(),
-        subcerts: vec![],
-        keylen: key.rsa_len(),
-        curve: key.curve()
+/// Request for an authority to be signed by an issuer outside of the PKI, signed
+/// with its own key and asking for the CA extensions of [`create_x509_node`].
+pub fn create_x509_req(args: CertArgs) -> Result<X509Req, ErrorStack> {
+    let name: X509Name = _get_name(&args.subject, &args.name)?;
+    let private_key: PKey<Private> = args.authority_pkey.to_private_pkey()?;
+
+    let mut req_builder: X509ReqBuilder = X509ReqBuilder::new()?;
+    req_builder.set_version(0)?;
+    req_builder.set_subject_name(&name)?;
+    req_builder.set_pubkey(&args.public_key)?;
+
+    let mut extensions = Stack::new()?;
+    extensions.push(_get_basic_constraints(args.path_len)?)?;
+    extensions.push(KeyUsage::new().critical().key_cert_sign().crl_sign().build()?)?;
+
+    if let Some(name_constraints) = &args.name_constraints {
+        extensions.push(name_constraints.to_extension()?)?;
+    }
+
+    for extension in policy_extensions(&args.policies, args.policy_constraints.as_ref(), args.inhibit_any_policy)? {
+        extensions.push(extension)?;
     }
+
+    for extension in args.extensions.iter() {
+        extensions.push(extension.to_extension()?)?;
+    }
+    extensions.push(_subject_key_identifier(&args.subject_key_id)?)?;
+
+    if let Some(san) = &args.san {
+        let mut san_builder = SubjectAlternativeName::new();
+
+        san.dns.iter().for_each(|dns| { san_builder.dns(dns); });
+        san.ip.iter().for_each(|ip| { san_builder.ip(ip); });
+        san.email.iter().for_each(|email| { san_builder.email(email); });
+        san.uri.iter().for_each(|uri| { san_builder.uri(uri); });
+
+        extensions.push(san_builder.build(&req_builder.x509v3_context(None))?)?;
+    }
+
+    req_builder.add_extensions(&extensions)?;
+
+    let req = req_builder.build();
+    sign_req(&req, &private_key, args.signature)?;
+
+    Ok(req)
+}
+
+
+/// Common name, key type and alternative names of a certificate. A common name that cannot
+/// be read as UTF-8 is an error.
+pub fn x509_to_certificate(cert: &X509) -> Result<Certificate, PKIError> {
+    let (keylen, curve) = cert.public_key()
+        .map(|pkey| Key::key_type(&pkey))
+        .unwrap_or_default();
+
+    let cname = match cert.subject_name().entries_by_nid(openssl::nid::Nid::COMMONNAME).next() {
+        Some(entry) => entry.data().as_utf8()
+            .map_err(|_| PKIError::Inconsistent("certificate common name is not a valid string".to_string()))?
+            .to_string(),
+        None => String::new()
+    };
+
+    Ok(Certificate {
+        cname,
+        subcerts: vec![],
+        ca: false,
+        keylen,
+        curve,
+        san: _get_alt_names(cert),
+        ..Certificate::default()
+    })
+}
+
+
+/// Parse a `YYYY-MM-DD` date, or an ASN.1 `YYYYMMDDHHMMSSZ` time
+pub fn parse_time(value: &str) -> Option<Asn1Time> {
+    let time = match value.len() {
+        10 => format!("{}000000Z", value.replace('-', "")),
+        _ => value.to_string()
+    };
+
+    Asn1Time::from_str(&time).ok()
+}
+
+
+/// Extension with a DER encoded value the openssl crate has no builder for
+pub(crate) fn der_extension(oid: &str, critical: bool, value: &[u8]) -> Result<X509Extension, ErrorStack> {
+    let oid = Asn1Object::from_str(oid)?;
+    let value = Asn1OctetString::new_from_bytes(value)?;
+
+    X509Extension::new_from_der(&oid, critical, &value)
 }
 
 
 // Private
-fn _get_name_builder(cert_conf: &X509Info) -> Result<X509NameBuilder, ErrorStack> {
+fn _get_name(subject: &Subject, common_name: &str) -> Result<X509Name, ErrorStack> {
     let mut name_builder: X509NameBuilder = X509NameBuilder::new()?;
 
-    name_builder.append_entry_by_text("C", &cert_conf.country)?;
-    name_builder.append_entry_by_text("ST",  &cert_conf.state)?;
-    name_builder.append_entry_by_text("O", &cert_conf.organization)?;
+    for dc in subject.domain_components.iter() {
+        name_builder.append_entry_by_text("DC", dc)?;
+    }
+
+    let attributes = [
+        ("C", &subject.country),
+        ("ST", &subject.state),
+        ("L", &subject.locality),
+        ("O", &subject.organization),
+    ];
+
+    for (field, value) in attributes {
+        if let Some(value) = value {
+            name_builder.append_entry_by_text(field, value)?;
+        }
+    }
+
+    for ou in subject.organizational_unit.iter() {
+        name_builder.append_entry_by_text("OU", ou)?;
+    }
+
+    name_builder.append_entry_by_text("CN", common_name)?;
+
+    if let Some(serial_number) = &subject.serial_number {
+        name_builder.append_entry_by_text("serialNumber", serial_number)?;
+    }
+
+    if let Some(email) = &subject.email {
+        name_builder.append_entry_by_text("emailAddress", email)?;
+    }
+
+    let mut rdns = subject.rdns.iter().peekable();
+
+    while let Some(rdn) = rdns.next_if(|rdn| rdn.len() == 1) {
+        for (field, value) in rdn.iter() {
+            name_builder.append_entry_by_text(field, value)?;
+        }
+    }
+
+    let mut name = name_builder.build();
 
-    Ok(name_builder)
+    // From the first multi-valued RDN on, the entries are added to the built name
+    for rdn in rdns {
+        _append_rdn(&mut name, rdn)?;
+    }
+
+    Ok(name)
+}
+
+
+/// Append a, possibly multi-valued, RDN to an owned name, which X509NameBuilder cannot do
+fn _append_rdn(name: &mut X509Name, rdn: &BTreeMap<String, String>) -> Result<(), ErrorStack> {
+    let fields = rdn.keys()
+        .map(|field| CString::new(field.as_str()).map_err(|_| ErrorStack::get()))
+        .collect::<Result<Vec<CString>, ErrorStack>>()?;
+
+    // SAFETY: the name is exclusively borrowed and outlives the calls, each field is a NUL
+    // terminated string and each value is passed with its length. X509_NAME_add_entry_by_txt
+    // copies both. `set` 0 starts a new RDN, -1 adds to the last one.
+    let added = unsafe {
+        fields.iter().zip(rdn.values()).enumerate().all(|(index, (field, value))| {
+            ffi::X509_NAME_add_entry_by_txt(
+                name.as_ptr(),
+                field.as_ptr(),
+                ffi::MBSTRING_UTF8,
+                value.as_ptr(),
+                value.len() as c_int,
+                -1,
+                if index == 0 { 0 } else { -1 }
+            ) > 0
+        })
+    };
+
+    match added {
+        true => Ok(()),
+        false => Err(ErrorStack::get())
+    }
 }
 
 
-fn _get_x509_builder(cert_conf: &X509Info, key: &PKey<Public>) -> Result<X509Builder, ErrorStack> {
+fn _get_x509_builder(serial: &BigNum, not_before: &Asn1Time, not_after: &Asn1Time, key: &PKey<Public>) -> Result<X509Builder, ErrorStack> {
     let mut x509_builder : X509Builder= X509::builder()?;
 
     x509_builder.set_version(2)?;
 
-    x509_builder.set_not_before(
-        &Asn1Time::days_from_now(0).unwrap()
-    )?;
-    x509_builder.set_not_after(
-        &Asn1Time::days_from_now(cert_conf.validity).unwrap()
-    )?;
+    x509_builder.set_not_before(not_before)?;
+    x509_builder.set_not_after(not_after)?;
 
-    let serial_number: Asn1Integer= {
-        let mut serial: BigNum = BigNum::new()?;
-        serial.rand(256, MsbOption::MAYBE_ZERO, false)?;
-        serial.to_asn1_integer()?
-    };
+    let serial_number: Asn1Integer = serial.to_asn1_integer()?;
     x509_builder.set_serial_number(&serial_number)?;
 
-    x509_builder.set_pubkey(&key)?;
+    x509_builder.set_pubkey(key)?;
 
     Ok(x509_builder)
 }
 
 
-fn _get_x509_req(pkey: &PKey<Private>, name_builder: X509NameBuilder) -> Result<X509Req, ErrorStack> {
-    let mut req_builder: X509ReqBuilder = X509ReqBuilder::new()?;
+fn _append_alt_names(cert_builder: &mut X509Builder, san: &AltNames) -> Result<(), ErrorStack> {
+    let mut san_builder = SubjectAlternativeName::new();
+
+    san.dns.iter().for_each(|dns| { san_builder.dns(dns); });
+    san.ip.iter().for_each(|ip| { san_builder.ip(ip); });
+    san.email.iter().for_each(|email| { san_builder.email(email); });
+    san.uri.iter().for_each(|uri| { san_builder.uri(uri); });
+
+    let extension = san_builder.build(&cert_builder.x509v3_context(None, None))?;
+
+    cert_builder.append_extension(extension)
+}
 
-    req_builder.set_pubkey(&pkey)?;
-    req_builder.set_subject_name(&name_builder.build())?;
-    req_builder.sign(&pkey, MessageDigest::sha3_256())?;
 
-    Ok(req_builder.build())
-}
\ No newline at end of file
+fn _get_basic_constraints(path_len: Option<u32>) -> Result<X509Extension, ErrorStack> {
+    let mut basic_constraints = BasicConstraints::new();
+    basic_constraints.critical().ca();
+
+    if let Some(path_len) = path_len {
+        basic_constraints.pathlen(path_len);
+    }
+
+    basic_constraints.build()
+}
+
+
+/// Subject Key Identifier, and Authority Key Identifier unless self-signed
+fn _append_key_identifiers(
+    cert_builder: &mut X509Builder,
+    subject_key_id: &[u8],
+    authority_key_id: Option<&[u8]>
+) -> Result<(), ErrorStack> {
+    cert_builder.append_extension(_subject_key_identifier(subject_key_id)?)?;
+
+    if let Some(key_id) = authority_key_id {
+        cert_builder.append_extension(authority_key_identifier(key_id)?)?;
+    }
+
+    Ok(())
+}
+
+
+/// Authority Key Identifier holding only the key identifier of the signing key
+pub(crate) fn authority_key_identifier(key_id: &[u8]) -> Result<X509Extension, ErrorStack> {
+    der_extension(AUTHORITY_KEY_ID_OID, false, &der::sequence(&[der::implicit(0, key_id)]))
+}
+
+
+fn _subject_key_identifier(key_id: &[u8]) -> Result<X509Extension, ErrorStack> {
+    der_extension(SUBJECT_KEY_ID_OID, false, &der::tlv(der::TAG_OCTET_STRING, key_id))
+}
+
+
+fn _get_profile_extensions(profile: Profile, key: &PKey<Public>) -> Result<Vec<X509Extension>, ErrorStack> {
+    let mut key_usage = KeyUsage::new();
+    let mut extended_key_usage = ExtendedKeyUsage::new();
+
+    key_usage.critical().digital_signature();
+
+    // Key transport only makes sense with RSA keys
+    let key_encipherment = key.id() == Id::RSA;
+
+    match profile {
+        Profile::TlsServer => {
+            extended_key_usage.server_auth();
+        },
+        Profile::TlsClient => {
+            extended_key_usage.client_auth();
+        },
+        Profile::Mtls => {
+            extended_key_usage.server_auth().client_auth();
+        },
+        Profile::CodeSigning => {
+            extended_key_usage.code_signing();
+        },
+        Profile::EmailProtection => {
+            key_usage.non_repudiation();
+            extended_key_usage.email_protection();
+        },
+        Profile::OcspSigning => {
+            extended_key_usage.other("OCSPSigning");
+        },
+        Profile::Timestamping => {
+            key_usage.non_repudiation();
+            // RFC 3161 requires this extension to be critical
+            extended_key_usage.critical().time_stamping();
+        },
+    }
+
+    if key_encipherment && matches!(profile, Profile::TlsServer | Profile::Mtls | Profile::EmailProtection) {
+        key_usage.key_encipherment();
+    }
+
+    let mut extensions = vec![
+        BasicConstraints::new().critical().build()?,
+        key_usage.build()?,
+        extended_key_usage.build()?
+    ];
+
+    // Delegated OCSP responders are not checked for revocation themselves
+    if profile == Profile::OcspSigning {
+        extensions.push(der_extension(NO_CHECK_OID, false, &der::null())?);
+    }
+
+    Ok(extensions)
+}
+
+
+fn _get_alt_names(cert: &X509) -> Option<AltNames> {
+    let mut san = AltNames::default();
+
+    for name in cert.subject_alt_names()?.iter() {
+        if let Some(dns) = name.dnsname() {
+            san.dns.push(dns.to_string());
+        } else if let Some(ip) = name.ipaddress() {
+            let ip = match ip.len() {
+                4 => IpAddr::from(Ipv4Addr::from(<[u8; 4]>::try_from(ip).unwrap())),
+                _ => IpAddr::from(Ipv6Addr::from(<[u8; 16]>::try_from(ip).ok()?)),
+            };
+
+            san.ip.push(ip.to_string());
+        } else if let Some(email) = name.email() {
+            san.email.push(email.to_string());
+        } else if let Some(uri) = name.uri() {
+            san.uri.push(uri.to_string());
+        }
+    }
+
+    Some(san)
+}
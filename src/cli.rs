@@ -1,22 +1,42 @@
-// Todo: Add CLI functionality for the pkimgr module
 // Todo: pkimgr submodule should not be pubic
 use std::{
     collections::HashMap,
-    fs::{create_dir_all, File},
+    fs::{create_dir_all, read, read_dir, remove_file, File, OpenOptions},
     io::{BufReader, Write},
     path::{Path, PathBuf},
 };
+#[cfg(unix)]
+use std::{
+    fs::{set_permissions, Permissions},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt}
+};
 
-use log::{info, debug, error};
+use log::{info, debug, warn};
+use openssl::x509::{X509Req, X509};
 
 use crate::{
-    certificates::Certificate,
+    certificates::{
+        crl::{Revocation, RevocationReason},
+        csr::{read_csr, CsrPolicy},
+        Certificate
+    },
     Configuration,
-    error::ManagerError,
+    error::{ManagerError, PKIError},
     key::Key,
+    passphrase::{EncryptedKeys, Passphrase},
     pki::{Pki, PkiJSON},
+    report::{EntryInfo, VerifyFailure},
+    responder::OcspResponder,
     CERTS_DIR,
-    PEM_DIR
+    CRL_DIR,
+    CSR_DIR,
+    METADATA_FILE,
+    PEM_DIR,
+    PKCS12_DIR,
+    RETIRED_DIR,
+    REVOCATIONS_FILE,
+    ROLLOVER_DIR,
+    SERIALS_FILE
 };
 
 #[derive(Clone)]
@@ -24,6 +44,8 @@ pub struct Pkimgr {
     default_conf: Configuration,
     base_path: PathBuf,
     pki: HashMap<String, Pki>,
+    key_passphrase: Option<String>,
+    encrypted_keys: EncryptedKeys,
 }
 
 impl Pkimgr {
@@ -31,41 +53,414 @@ impl Pkimgr {
         Pkimgr {
             default_conf: configuration,
             base_path,
-            pki: HashMap::new()
+            pki: HashMap::new(),
+            key_passphrase: None,
+            encrypted_keys: EncryptedKeys::default()
         }
     }
 
 
-    pub fn create_from_file(self: &mut Self, pki_file: File) -> Result<&Self, ManagerError> {
+    /// Passphrase used to decrypt keys when loading a PKI, and to encrypt the
+    /// selected keys when saving one.
+    pub fn set_key_passphrase(&mut self, passphrase: &Passphrase, encrypted_keys: EncryptedKeys) -> Result<&Self, ManagerError> {
+        self.key_passphrase = Some(passphrase.resolve()?);
+        self.encrypted_keys = encrypted_keys;
+
+        Ok(self)
+    }
+
+
+    pub fn create_from_file(&mut self, pki_file: File) -> Result<&Self, ManagerError> {
         let json: PkiJSON = serde_json::from_reader(
             BufReader::new(pki_file)
         )?;
 
+        json.root.validate().map_err(ManagerError::InvalidSpec)?;
+
+        if let Some(access_urls) = &json.access_urls {
+            access_urls.validate().map_err(|err| ManagerError::InvalidSpec(format!("access_urls: {}", err)))?;
+        }
+
         self.new_pki(&json.pki_name, None);
 
+        let pki = self.pki_from_name_as_mut(&json.pki_name)?;
+        pki.json.digest = json.digest;
+        pki.json.rsa_pss = json.rsa_pss;
+        pki.json.key_identifier = json.key_identifier;
+        pki.json.access_urls = json.access_urls;
+
         // Add Root Authority
-        let key = Key::new(json.root.keylen, json.root.curve)?;
+        let key = Key::new(json.root.keylen, json.root.curve.clone())?;
+
+        self.create_authority(&json.pki_name, None, &json.root, key)?;
 
-        self.create_authority(&json.pki_name, None, &json.root.cname, key)?;
+        if json.root.external_issuer {
+            info!(
+                "{} waits for its certificate, have {}/{}/{}.csr signed then import it",
+                &json.root.cname, Path::join(&self.base_path, &json.pki_name).display(), CSR_DIR, &json.root.cname
+            );
 
+            return self.save();
+        }
+
+        // A refused entry fails the whole PKI, nothing is written
         for cert in json.root.subcerts {
-            match self.add_recursive_cert(&json.pki_name, &json.root.cname, cert) {
-                Ok(_) => info!("PKI {} successfully created", &json.pki_name),
-                Err(err) => error!("Cannot create PKI {}: {}", &json.pki_name, err)
-            }
+            self.add_recursive_cert(&json.pki_name, &json.root.cname, cert)?;
         }
 
         debug!("PKI {} created, save it", json.pki_name);
+        self.save()?;
+        info!("PKI {} successfully created", &json.pki_name);
+
+        Ok(self)
+    }
+
+
+    /// Reopen a PKI previously written by [`Pkimgr::save`] under `base_path`.
+    pub fn load(&mut self, pki_name: &String, config: Option<Configuration>) -> Result<&Self, ManagerError> {
+        let path = Path::join(&self.base_path, pki_name);
+
+        let json: PkiJSON = serde_json::from_reader(
+            BufReader::new(File::open(Path::join(&path, METADATA_FILE))?)
+        )?;
+
+        if json.pki_name != *pki_name {
+            return Err(ManagerError::NotFound(
+                format!("{} metadata describes {}", pki_name, json.pki_name)
+            ));
+        }
+
+        let configuration = config.unwrap_or_else(|| self.default_conf.clone());
+        let passphrase = self.key_passphrase.as_ref().map(|passphrase| passphrase.as_bytes());
+        let root_cert = Path::join(&path, CERTS_DIR).join(format!("{}.crt", &json.root.cname));
+
+        if json.root.external_issuer && !root_cert.exists() {
+            let req = X509Req::from_pem(&read(Path::join(&path, CSR_DIR).join(format!("{}.csr", &json.root.cname)))?)?;
+            let key = read_key(&path, &json.root.cname, passphrase)?;
+
+            debug!("PKI {} loaded, waiting for {} certificate", pki_name, &json.root.cname);
+            self.pki.insert(pki_name.to_owned(), Pki::load_pending(json, configuration, req, key)?);
+
+            return Ok(self);
+        }
+
+        let mut entries = HashMap::new();
+        read_entries(&path, &json.root, passphrase, &mut entries)?;
+
+        for file in read_dir(Path::join(&path, CERTS_DIR))? {
+            let file_name = file?.file_name().to_string_lossy().to_string();
+
+            match file_name.strip_suffix(".crt") {
+                Some(name) if entries.contains_key(name) => (),
+                _ => warn!("{} is not described in {} metadata, ignoring it", file_name, pki_name)
+            }
+        }
+
+        let mut pki = Pki::load(json, configuration, entries, read_retired(&path, passphrase)?)?;
+
+        let revocations_path = Path::join(&path, REVOCATIONS_FILE);
+        if revocations_path.exists() {
+            pki.revocations = serde_json::from_reader(BufReader::new(File::open(revocations_path)?))?;
+        }
+
+        let serials_path = Path::join(&path, SERIALS_FILE);
+        if serials_path.exists() {
+            pki.serials = serde_json::from_reader(BufReader::new(File::open(serials_path)?))?;
+        }
+
+        pki.index_serials()?;
+
+        debug!("PKI {} loaded", pki_name);
+        self.pki.insert(pki_name.to_owned(), pki);
+
+        Ok(self)
+    }
+
+
+    /// Issue a certificate, or a sub authority with its own subcerts, under an
+    /// existing authority of a saved PKI. Only the new entries are written.
+    pub fn issue(&mut self, pki_name: &String, auth_name: &String, cert: Certificate) -> Result<&Self, ManagerError> {
+        cert.validate().map_err(ManagerError::InvalidSpec)?;
+
+        self.loaded_pki_as_mut(pki_name)?;
+
+        let cname = cert.cname.to_owned();
+
+        self.add_recursive_cert(pki_name, auth_name, cert)?;
+
+        info!("{} issued by {} on {}", cname, auth_name, pki_name);
         self.save()
     }
 
 
-    pub fn get_pki(self: &Self) -> Vec<&String> {
+    /// Install the certificate an external issuer returned for the root authority of a saved
+    /// PKI, then issue the sub certificates of its specification.
+    pub fn import_signed(&mut self, pki_name: &String, cert: &Path) -> Result<&Self, ManagerError> {
+        let data = read(cert)?;
+        let cert = match data.starts_with(b"-----") {
+            true => X509::from_pem(&data)?,
+            false => X509::from_der(&data)?
+        };
+        let pki = self.loaded_pki_as_mut(pki_name)?;
+        let root = pki.json.root.cname.to_owned();
+
+        for subcert in pki.import_signed(cert)? {
+            self.add_recursive_cert(pki_name, &root, subcert)?;
+        }
+
+        info!("{} certificate imported on {}", root, pki_name);
+        self.save()
+    }
+
+
+    /// Sign a PEM or DER PKCS#10 request with an authority of a saved PKI. `cert` holds the
+    /// certificate settings, and its common name when the request has none. Returns the
+    /// summary of the signed entry.
+    pub fn sign_csr(
+        &mut self,
+        pki_name: &String,
+        auth_name: &String,
+        csr: &Path,
+        cert: Certificate,
+        policy: CsrPolicy,
+        allowed_domains: &[String]
+    ) -> Result<EntryInfo, ManagerError> {
+        let req = read_csr(&read(csr)?)?;
+        let pki = self.loaded_pki_as_mut(pki_name)?;
+
+        let cname = pki.sign_csr(&cert, auth_name, &req, policy, allowed_domains)?;
+
+        info!("{} signed by {} on {}", csr.display(), auth_name, pki_name);
+        self.save()?;
+        self.show(pki_name, &cname)
+    }
+
+
+    /// Reissue an entry of a saved PKI with its key, a new serial number and validity period.
+    pub fn renew(&mut self, pki_name: &String, cert_name: &String, validity: Option<u32>) -> Result<&Self, ManagerError> {
+        self.loaded_pki_as_mut(pki_name)?.renew(cert_name, validity)?;
+
+        info!("{} renewed on {}", cert_name, pki_name);
+        self.save()
+    }
+
+
+    /// Reissue an entry of a saved PKI with a new key, of the same type unless `keylen` or
+    /// `curve` is set, and optionally the certificates it issued.
+    pub fn rekey(
+        &mut self,
+        pki_name: &String,
+        cert_name: &String,
+        keylen: Option<u32>,
+        curve: Option<String>,
+        validity: Option<u32>,
+        reissue_subordinates: bool
+    ) -> Result<&Self, ManagerError> {
+        let pki = self.loaded_pki_as_mut(pki_name)?;
+
+        let key = match (keylen, curve) {
+            (None, None) => {
+                let (current, _) = pki.get_entry(cert_name)?;
+                let public_key = current.public_key()?;
+                let (keylen, curve) = Key::key_type(&public_key);
+
+                Key::new(keylen, curve)?
+            },
+            (keylen, curve) => Key::new(keylen, curve)?
+        };
+
+        pki.rekey(cert_name, key, validity, reissue_subordinates)?;
+
+        info!("{} re-keyed on {}", cert_name, pki_name);
+        self.save()
+    }
+
+
+    /// Replace the root of a saved PKI. Both roots and their cross-certificates are written
+    /// under `rollover/<n>`, `n` counting the rollovers of the PKI.
+    pub fn rollover(
+        &mut self,
+        pki_name: &String,
+        keylen: Option<u32>,
+        curve: Option<String>,
+        validity: Option<u32>,
+        reissue_subordinates: bool
+    ) -> Result<&Self, ManagerError> {
+        let path = Path::join(&self.base_path, pki_name).join(ROLLOVER_DIR);
+        let pki = self.loaded_pki_as_mut(pki_name)?;
+
+        let key = match (keylen, curve) {
+            (None, None) => {
+                let root = &pki.json.root;
+
+                Key::new(root.keylen, root.curve.clone())?
+            },
+            (keylen, curve) => Key::new(keylen, curve)?
+        };
+
+        let rollover = pki.rollover(key, validity, reissue_subordinates)?;
+
+        create_dir_all(&path)?;
+        let generation = read_dir(&path)?.count() + 1;
+        let path = Path::join(&path, generation.to_string());
+        create_dir_all(&path)?;
+
+        let certificates = [
+            ("old-root.crt", &rollover.old_root),
+            ("new-root.crt", &rollover.new_root),
+            ("old-signed-by-new.crt", &rollover.old_with_new),
+            ("new-signed-by-old.crt", &rollover.new_with_old),
+        ];
+
+        for (file_name, cert) in certificates {
+            File::create(Path::join(&path, file_name))?.write_all(&cert.to_pem()?)?;
+        }
+
+        info!("Root of {} rolled over, certificates written in {}", pki_name, path.display());
+        self.save()
+    }
+
+
+    /// Summary of every entry of a saved PKI.
+    pub fn list(&mut self, pki_name: &String) -> Result<Vec<EntryInfo>, ManagerError> {
+        Ok(self.loaded_pki_as_mut(pki_name)?.entries()?)
+    }
+
+
+    /// Summary of an entry of a saved PKI.
+    pub fn show(&mut self, pki_name: &String, cert_name: &String) -> Result<EntryInfo, ManagerError> {
+        Ok(self.loaded_pki_as_mut(pki_name)?.entry_info(cert_name)?)
+    }
+
+
+    /// Verify the chain of every entry of a saved PKI, returning the failures.
+    pub fn verify(&mut self, pki_name: &String) -> Result<Vec<VerifyFailure>, ManagerError> {
+        Ok(self.loaded_pki_as_mut(pki_name)?.verify()?)
+    }
+
+
+    /// Revoke a certificate of a saved PKI by its common name.
+    pub fn revoke(&mut self, pki_name: &String, cert_name: &String, reason: RevocationReason) -> Result<Revocation, ManagerError> {
+        let revocation = self.loaded_pki_as_mut(pki_name)?.revoke(cert_name, reason)?;
+
+        info!("{} revoked on {} ({})", cert_name, pki_name, reason);
+        self.save()?;
+
+        Ok(revocation)
+    }
+
+
+    /// Revoke a serial number issued by an authority of a saved PKI.
+    pub fn revoke_serial(
+        &mut self,
+        pki_name: &String,
+        auth_name: &String,
+        serial: &str,
+        reason: RevocationReason
+    ) -> Result<Revocation, ManagerError> {
+        let revocation = self.loaded_pki_as_mut(pki_name)?.revoke_serial(auth_name, serial, None, reason)?;
+
+        info!("{} revoked by {} on {} ({})", serial, auth_name, pki_name, reason);
+        self.save()?;
+
+        Ok(revocation)
+    }
+
+
+    /// Write a new CRL, in PEM and DER, for one or every authority of a saved PKI, and for each
+    /// of their retired keys still valid, returning the files written.
+    pub fn generate_crl(&mut self, pki_name: &String, auth_name: Option<&String>, next_update: u32) -> Result<Vec<PathBuf>, ManagerError> {
+        let path = Path::join(&self.base_path, pki_name).join(CRL_DIR);
+        let pki = self.loaded_pki_as_mut(pki_name)?;
+
+        let authorities: Vec<String> = match auth_name {
+            Some(name) => vec![name.to_owned()],
+            None => pki.authorities.keys().cloned().collect()
+        };
+
+        let mut written = Vec::new();
+
+        create_dir_all(&path)?;
+
+        for name in authorities {
+            // Retired keys sign the CRLs of the certificates issued before a re-key, as `<cname>-<serial>.crl`
+            let retired = pki.retired_crls(&name, next_update)?.into_iter()
+                .map(|(serial, crl)| (format!("{}-{}", &name, serial), crl));
+
+            for (file_name, crl) in std::iter::once((name.to_owned(), pki.crl(&name, next_update)?)).chain(retired) {
+                let der_path = Path::join(&path, format!("{}.crl", &file_name));
+                let pem_path = Path::join(&path, format!("{}.crl.pem", &file_name));
+
+                File::create(&der_path)?.write_all(&crl.to_der()?)?;
+                File::create(&pem_path)?.write_all(&crl.to_pem()?)?;
+                written.extend([der_path, pem_path]);
+            }
+
+            info!("CRL of {} written on {}", name, pki_name);
+        }
+
+        self.save()?;
+
+        Ok(written)
+    }
+
+
+    /// Write PKCS#12 bundles for one or every entry of a saved PKI, returning the files written.
+    pub fn export_pkcs12(
+        &mut self,
+        pki_name: &String,
+        cert_name: Option<&String>,
+        passphrase: &Passphrase,
+        friendly_name: Option<&str>
+    ) -> Result<Vec<PathBuf>, ManagerError> {
+        let path = Path::join(&self.base_path, pki_name).join(PKCS12_DIR);
+        let passphrase = passphrase.resolve()?;
+        let pki = self.loaded_pki_as_mut(pki_name)?;
+
+        let names: Vec<String> = match cert_name {
+            Some(name) => vec![name.to_owned()],
+            None => pki.authorities.keys().chain(pki.certs.keys()).cloned().collect()
+        };
+
+        let mut written = Vec::new();
+
+        create_dir_all(&path)?;
+
+        for name in names {
+            let bundle_path = Path::join(&path, format!("{}.p12", &name));
+
+            File::create(&bundle_path)?.write_all(&pki.pkcs12(&name, &passphrase, friendly_name)?)?;
+            written.push(bundle_path);
+
+            info!("PKCS#12 bundle of {} written on {}", name, pki_name);
+        }
+
+        Ok(written)
+    }
+
+
+    /// Answer OCSP requests for the authorities of a saved PKI until interrupted.
+    pub fn ocsp_serve(
+        &mut self,
+        pki_name: &String,
+        listen: &str,
+        responders: Vec<String>,
+        next_update: u32
+    ) -> Result<&Self, ManagerError> {
+        let pki = self.loaded_pki_as_mut(pki_name)?;
+
+        OcspResponder::new(pki, responders, next_update)?.serve(listen)?;
+
+        Ok(self)
+    }
+
+
+    pub fn get_pki(&self) -> Vec<&String> {
         self.pki.keys().collect()
     }
 
 
-    pub fn new_pki(self: &mut Self, pki_name: &String, config: Option<Configuration>) -> &Self  {
+    pub fn new_pki(&mut self, pki_name: &String, config: Option<Configuration>) -> &Self  {
         let configuration = match config {
             None => self.default_conf.clone(),
             Some(c) => c
@@ -83,55 +478,98 @@ impl Pkimgr {
 
 
     pub fn create_authority(
-        self: &mut Self,
+        &mut self,
         pki_name: &String,
         auth_name: Option<&String>,
-        cert_name: &String,
+        cert: &Certificate,
         key: Key
     ) -> Result<&Self, ManagerError> {
         let pki = self.pki_from_name_as_mut(pki_name)?;
 
-        pki.add_authority(cert_name, auth_name, key)?;
+        pki.add_authority(cert, auth_name, key)?;
 
         Ok(self)
     }
 
 
     pub fn create_certificate(
-        self: &mut Self,
+        &mut self,
         pki_name: &String,
-        cert_name: &String,
+        cert: &Certificate,
         auth_name: &String,
         key: Key
     ) -> Result<&Self, ManagerError> {
         let pki = self.pki_from_name_as_mut(pki_name)?;
 
-        pki.add_certificate(cert_name, auth_name, key)?;
+        pki.add_certificate(cert, auth_name, key)?;
 
         Ok(self)
     }
 
 
-    pub fn save(self: &Self) -> Result<&Self, ManagerError> {
-        for (_, pki) in self.pki.clone() {
-            let path = Path::join(&self.base_path, pki.name);
+    /// Write the entries added since the last save, then the metadata, revocations
+    /// and serial numbers of every PKI.
+    pub fn save(&mut self) -> Result<&Self, ManagerError> {
+        for pki in self.pki.values_mut() {
+            let modified = pki.take_modified();
+            let path = Path::join(&self.base_path, &pki.name);
 
             if !path.exists() {
                 create_dir_all(Path::join(&path, CERTS_DIR))?;
-                create_dir_all(Path::join(&path, PEM_DIR))?;
+                create_private_dir(&Path::join(&path, PEM_DIR))?;
             }
 
+            for name in modified {
+                if pki.retired.contains_key(&name) {
+                    remove_retired(&path, &name)?;
+                }
 
-            for (name, (cert, key)) in pki.authorities.clone() {
-                write_cert_file(&path, &name, cert.to_pem()?, key.to_pem()?)?;
+                for (retired, key) in pki.retired.get(&name).into_iter().flatten() {
+                    let serial = retired.serial_number().to_bn()?.to_hex_str()?.to_string();
+                    let retired_path = Path::join(&path, RETIRED_DIR);
+                    let passphrase = self.key_passphrase.as_deref().filter(|_| self.encrypted_keys.covers(true));
+
+                    create_private_dir(&Path::join(&retired_path, PEM_DIR))?;
+                    File::create(Path::join(&retired_path, format!("{}-{}.crt", &name, serial)))?
+                        .write_all(&retired.to_pem()?)?;
+                    write_key_file(&retired_path, &format!("{}-{}", &name, serial), private_key_pem(key, passphrase)?)?;
+                }
+
+                if let Some((req, key)) = pki.pending.get(&name) {
+                    create_dir_all(Path::join(&path, CSR_DIR))?;
+                    File::create(Path::join(&path, CSR_DIR).join(format!("{}.csr", &name)))?
+                        .write_all(req)?;
+
+                    let passphrase = self.key_passphrase.as_deref().filter(|_| self.encrypted_keys.covers(true));
+
+                    write_key_file(&path, &name, private_key_pem(key, passphrase)?)?;
+                    continue;
+                }
+
+                if let Some(cert) = pki.external.get(&name) {
+                    write_cert_file(&path, &name, cert.to_pem()?, None)?;
+                    continue;
+                }
+
+                let (cert, key) = pki.get_entry(&name)?;
+                let authority = pki.authorities.contains_key(&name);
+                let passphrase = self.key_passphrase.as_deref().filter(|_| self.encrypted_keys.covers(authority));
+
+                write_cert_file(&path, &name, cert.to_pem()?, Some(private_key_pem(key, passphrase)?))?;
             }
 
-            for (name, (cert, key)) in pki.certs.clone() {
-                write_cert_file(&path, &name, cert.to_pem()?, key.to_pem()?)?;
+            File::create(Path::join(&path, METADATA_FILE))?
+                .write_all(pki.json.to_string().as_bytes())?;
+
+            if !pki.revocations.is_empty() {
+                File::create(Path::join(&path, REVOCATIONS_FILE))?
+                    .write_all(serde_json::to_string_pretty(&pki.revocations)?.as_bytes())?;
             }
 
-            File::create(Path::join(&path, "metadata.json"))?
-                .write_all(format!("{}", pki.json).as_bytes())?;
+            if !pki.serials.is_empty() {
+                File::create(Path::join(&path, SERIALS_FILE))?
+                    .write_all(serde_json::to_string_pretty(&pki.serials)?.as_bytes())?;
+            }
         }
 
         Ok(self)
@@ -139,21 +577,30 @@ impl Pkimgr {
 
 
     // Private
-    fn pki_from_name_as_mut(self: &mut Self, pki_name: &String) -> Result<&mut Pki, ManagerError> {
+    fn pki_from_name_as_mut(&mut self, pki_name: &String) -> Result<&mut Pki, ManagerError> {
         self.pki.get_mut(pki_name)
             .ok_or_else(|| ManagerError::NotFound(format!("Cannot find {}", &pki_name)))
     }
 
 
-    fn add_recursive_cert(self: &mut Self, pki_name: &String, root: &String, cert: Certificate) -> Result<(), ManagerError> {
-        let key = Key::new(cert.keylen, cert.curve)?;
+    fn loaded_pki_as_mut(&mut self, pki_name: &String) -> Result<&mut Pki, ManagerError> {
+        if !self.pki.contains_key(pki_name) {
+            self.load(pki_name, None)?;
+        }
+
+        self.pki_from_name_as_mut(pki_name)
+    }
+
+
+    fn add_recursive_cert(&mut self, pki_name: &String, root: &String, cert: Certificate) -> Result<(), ManagerError> {
+        let key = Key::new(cert.keylen, cert.curve.clone())?;
 
-        if cert.subcerts.len() == 0 {
+        if cert.subcerts.is_empty() && !cert.ca {
             debug!("Adding certificate {}", &cert.cname);
-            self.create_certificate(pki_name, &cert.cname, root, key)?;
+            self.create_certificate(pki_name, &cert, root, key)?;
         } else {
             debug!("Adding sub CA {} root ({})", &cert.cname, root);
-            self.create_authority(pki_name, Some(root), &cert.cname, key)?;
+            self.create_authority(pki_name, Some(root), &cert, key)?;
 
             for sub_cert in cert.subcerts {
                 self.add_recursive_cert(pki_name, &cert.cname, sub_cert)?;
@@ -165,11 +612,133 @@ impl Pkimgr {
 }
 
 
-fn write_cert_file(path: &Path, name: &String, cert_pem: Vec<u8>, private_key: Vec<u8>) -> Result<(), ManagerError> {
-    File::create(format!(
-        "{}/{}.pem", Path::join(path, PEM_DIR).to_str().unwrap(),
-        &name
-    ))?.write_all(&private_key)?;
+fn read_entries(
+    path: &Path,
+    cert: &Certificate,
+    passphrase: Option<&[u8]>,
+    entries: &mut HashMap<String, (X509, Option<Key>)>
+) -> Result<(), ManagerError> {
+    let cert_pem = read(Path::join(path, CERTS_DIR).join(format!("{}.crt", &cert.cname)))?;
+
+    let key = match cert.external_key {
+        true => None,
+        false => Some(read_key(path, &cert.cname, passphrase)?)
+    };
+
+    entries.insert(cert.cname.to_owned(), (X509::from_pem(&cert_pem)?, key));
+
+    for subcert in cert.subcerts.iter() {
+        read_entries(path, subcert, passphrase, entries)?;
+    }
+
+    Ok(())
+}
+
+
+/// Retired authority certificates and keys, written as `<cname>-<serial>.crt` and
+/// `private/<cname>-<serial>.pem`
+fn read_retired(path: &Path, passphrase: Option<&[u8]>) -> Result<HashMap<String, Vec<(X509, Key)>>, ManagerError> {
+    let mut retired: HashMap<String, Vec<(X509, Key)>> = HashMap::new();
+    let path = Path::join(path, RETIRED_DIR);
+
+    if !path.exists() {
+        return Ok(retired);
+    }
+
+    for file in read_dir(&path)? {
+        let file = file?;
+        let file_name = file.file_name().to_string_lossy().to_string();
+
+        if file.file_type()?.is_dir() {
+            continue;
+        }
+
+        match file_name.strip_suffix(".crt").and_then(|stem| Some((stem, stem.rsplit_once('-')?.0))) {
+            Some((stem, name)) => retired.entry(name.to_string()).or_default().push((
+                X509::from_pem(&read(file.path())?)?,
+                read_key(&path, &stem.to_string(), passphrase)?
+            )),
+            None => warn!("{} is not a retired certificate, ignoring it", file_name)
+        }
+    }
+
+    Ok(retired)
+}
+
+
+fn remove_retired(path: &Path, name: &str) -> Result<(), ManagerError> {
+    let path = Path::join(path, RETIRED_DIR);
+
+    if !path.exists() {
+        return Ok(());
+    }
+
+    for file in read_dir(&path)? {
+        let file = file?;
+        let file_name = file.file_name().to_string_lossy().to_string();
+
+        if let Some(stem) = file_name.strip_suffix(".crt").filter(|stem| stem.rsplit_once('-').is_some_and(|(cname, _)| cname == name)) {
+            remove_file(file.path())?;
+            remove_file(Path::join(&path, PEM_DIR).join(format!("{}.pem", stem)))?;
+        }
+    }
+
+    Ok(())
+}
+
+
+fn read_key(path: &Path, name: &String, passphrase: Option<&[u8]>) -> Result<Key, ManagerError> {
+    let private_key = read(Path::join(path, PEM_DIR).join(format!("{}.pem", name)))?;
+
+    Ok(Key::from_pem(&private_key, passphrase).map_err(|err| match err {
+        PKIError::EncryptedKey(reason) => PKIError::EncryptedKey(format!("{}: {}", name, reason)),
+        err => err
+    })?)
+}
+
+
+/// PEM of a key, encrypted with `passphrase` when set
+fn private_key_pem(key: &Key, passphrase: Option<&str>) -> Result<Vec<u8>, ManagerError> {
+    Ok(match passphrase {
+        Some(passphrase) => key.to_encrypted_pem(passphrase.as_bytes())?,
+        None => key.to_pem()?
+    })
+}
+
+
+fn create_private_dir(path: &Path) -> Result<(), ManagerError> {
+    create_dir_all(path)?;
+
+    #[cfg(unix)]
+    set_permissions(path, Permissions::from_mode(0o700))?;
+
+    Ok(())
+}
+
+
+fn write_key_file(path: &Path, name: &String, private_key: Vec<u8>) -> Result<(), ManagerError> {
+    let mut key_file = OpenOptions::new();
+    key_file.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    key_file.mode(0o600);
+
+    let key_path = Path::join(path, PEM_DIR).join(format!("{}.pem", name));
+
+    key_file.open(&key_path)?.write_all(&private_key)?;
+
+    // The mode above only applies to new files
+    #[cfg(unix)]
+    set_permissions(&key_path, Permissions::from_mode(0o600))?;
+
+    Ok(())
+}
+
+
+fn write_cert_file(path: &Path, name: &String, cert_pem: Vec<u8>, private_key: Option<Vec<u8>>) -> Result<(), ManagerError> {
+    if let Some(private_key) = private_key {
+        write_key_file(path, name, private_key)?;
+    }
 
     File::create(format!(
         "{}/{}.crt", Path::join(path, CERTS_DIR).to_str().unwrap(),
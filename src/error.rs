@@ -8,7 +8,12 @@ use openssl::error::ErrorStack;
 pub enum PKIError {
     NotFound(String),
     OpenSSL(ErrorStack),
-    JsonError(String)
+    JsonError(String),
+    Inconsistent(String),
+    UnsupportedKey(String),
+    AlreadyExists(String),
+    EncryptedKey(String),
+    InvalidSpec(String)
 }
 
 
@@ -25,7 +30,12 @@ impl fmt::Display for PKIError {
         match self {
             PKIError::NotFound(err) => write!(f, "{}", err),
             PKIError::OpenSSL(err) => write!(f, "{}", err),
-            PKIError::JsonError(err) => write!(f, "{}", err)
+            PKIError::JsonError(err) => write!(f, "{}", err),
+            PKIError::Inconsistent(err) => write!(f, "Inconsistent PKI: {}", err),
+            PKIError::UnsupportedKey(err) => write!(f, "Unsupported key: {}", err),
+            PKIError::AlreadyExists(err) => write!(f, "{}", err),
+            PKIError::EncryptedKey(err) => write!(f, "Encrypted key: {}", err),
+            PKIError::InvalidSpec(err) => write!(f, "Invalid specification: {}", err)
         }
     }
 }
@@ -36,12 +46,13 @@ pub enum ManagerError {
     IOError(String),
     PKIError(PKIError),
     JsonError(String),
-    OpenSSL(ErrorStack)
+    OpenSSL(ErrorStack),
+    InvalidSpec(String)
 }
 
 impl From<io::Error> for ManagerError {
     fn from(value: io::Error) -> Self {
-        ManagerError::IOError(format!("{}", value.to_string()))
+        ManagerError::IOError(value.to_string())
     }
 }
 
@@ -70,6 +81,7 @@ impl fmt::Display for ManagerError {
             ManagerError::JsonError(err) => write!(f, "JSONError: {}", err),
             ManagerError::NotFound(err) => write!(f, "NotfoundError: {}", err),
             ManagerError::PKIError(err) => write!(f, "PKIError: {}", err),
+            ManagerError::InvalidSpec(err) => write!(f, "InvalidSpec: {}", err),
             ManagerError::OpenSSL(err) => {
                 err
                     .errors()
@@ -8,6 +8,9 @@ pub mod pki;
 
 pub mod cli;
 pub mod error;
+pub mod passphrase;
+pub mod report;
+pub mod responder;
 
 pub const BANNER: &str = r#"
            __                    __  ____      __         __
@@ -23,8 +26,16 @@ pub const BANNER: &str = r#"
           rust edition        /____/
 "#;
 
-const PEM_DIR: &'static str = "private";
-const CERTS_DIR: &'static str = "certs";
+const PEM_DIR: &str = "private";
+const CERTS_DIR: &str = "certs";
+const CRL_DIR: &str = "crl";
+const CSR_DIR: &str = "csr";
+const RETIRED_DIR: &str = "retired";
+const ROLLOVER_DIR: &str = "rollover";
+const PKCS12_DIR: &str = "pkcs12";
+const METADATA_FILE: &str = "metadata.json";
+const REVOCATIONS_FILE: &str = "revocations.json";
+const SERIALS_FILE: &str = "serials.json";
 
 
 pub const DEFAULT_CONFIGURATION: &str = r#"
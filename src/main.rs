@@ -1,15 +1,20 @@
 use std::{
     fs::{self, File},
-    path::Path
+    path::{Path, PathBuf},
+    process::exit
 };
 
 use log::{info, error};
-use clap::Parser;
+use clap::{Args as ClapArgs, Parser, Subcommand};
 use env_logger::{init_from_env, Env};
+use serde::Serialize;
 
 use pkimgr::{
+    certificates::{crl::{Revocation, RevocationReason}, csr::CsrPolicy, signature::Digest, AltNames, Certificate, Profile, Subject},
     Configuration, DEFAULT_CONFIGURATION,
     cli::Pkimgr,
+    passphrase::{EncryptedKeys, Passphrase},
+    report::EntryInfo,
     BANNER
 };
 
@@ -18,25 +23,392 @@ use pkimgr::{
 #[command(author, version, about)]
 struct Args {
     /// Path to store the PKI
-    #[arg(short, long, default_value = ".")]
+    #[arg(short, long, default_value = ".", global = true)]
     path: String,
     /// Path of the configuration file to use
-    #[arg(short, long, default_value = "")]
+    #[arg(short, long, default_value = "", global = true)]
     configuration_file: String,
-    /// Path of the file describing the PKI
-    #[arg()]
-    pki_file: String
+    /// Print results as JSON, without banner
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(flatten)]
+    key_passphrase: KeyPassphraseArgs,
+    #[command(subcommand)]
+    command: Command
 }
 
-pub fn main() ->  () {
-    println!("{}", BANNER);
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a PKI from the file describing it
+    Init {
+        /// Path of the file describing the PKI
+        pki_file: PathBuf
+    },
+    /// Issue a new certificate under an authority of a saved PKI
+    Issue {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Common name of the issuing authority
+        #[arg(long)]
+        issuer: String,
+        /// Common name of the new certificate
+        #[arg(long)]
+        cn: String,
+        /// RSA key length
+        #[arg(long)]
+        keylen: Option<u32>,
+        /// Curve name: prime256v1, secp256k1, secp384r1, secp521r1, ed25519 or ed448, ec for prime256v1
+        #[arg(long)]
+        curve: Option<String>,
+        /// Issue a sub authority instead of a leaf certificate
+        #[arg(long)]
+        ca: bool,
+        /// Key usage profile of a leaf certificate [default: tls-server]
+        #[arg(long)]
+        profile: Option<Profile>,
+        /// Subject DN attribute as FIELD=VALUE, can be repeated. Join attributes with '+' for a multi-valued RDN, which replaces the default C, ST or O it holds
+        #[arg(long, value_parser = parse_rdn)]
+        subject: Vec<Vec<(String, String)>>,
+        /// Validity in days [default: configuration validity]
+        #[arg(long)]
+        validity: Option<u32>,
+        /// Start of validity, as YYYY-MM-DD or YYYYMMDDHHMMSSZ [default: now]
+        #[arg(long)]
+        not_before: Option<String>,
+        /// End of validity, as YYYY-MM-DD or YYYYMMDDHHMMSSZ
+        #[arg(long, conflicts_with = "validity")]
+        not_after: Option<String>,
+        /// Allow the certificate to expire after its issuer
+        #[arg(long)]
+        outlive_issuer: bool,
+        /// Signature digest: sha256, sha384, sha512, sha3-256, sha3-384 or sha3-512 [default: PKI or issuer key digest]
+        #[arg(long)]
+        digest: Option<Digest>,
+        /// Sign with RSASSA-PSS padding, when the issuer key is RSA
+        #[arg(long)]
+        rsa_pss: bool,
+        /// DNS Subject Alternative Name, can be repeated
+        #[arg(long)]
+        dns: Vec<String>,
+        /// IP address Subject Alternative Name, can be repeated
+        #[arg(long)]
+        ip: Vec<String>,
+        /// Email Subject Alternative Name, can be repeated
+        #[arg(long)]
+        email: Vec<String>,
+        /// URI Subject Alternative Name, can be repeated
+        #[arg(long)]
+        uri: Vec<String>
+    },
+    /// Sign an external PKCS#10 request with an authority of a saved PKI
+    SignCsr {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Common name of the issuing authority
+        #[arg(long)]
+        issuer: String,
+        /// Path of the PEM or DER request
+        #[arg(long)]
+        csr: PathBuf,
+        /// Common name of the certificate [default: requested common name]
+        #[arg(long)]
+        cn: Option<String>,
+        /// Key usage profile of the certificate [default: tls-server]
+        #[arg(long)]
+        profile: Option<Profile>,
+        /// Requested names to copy: common-name, alt-names or full
+        #[arg(long, default_value = "alt-names")]
+        policy: CsrPolicy,
+        /// Domain whose DNS names and email addresses the request may ask for, can be repeated.
+        /// Other requested alternative names must be given with --dns, --ip, --email or --uri
+        #[arg(long)]
+        allow_domain: Vec<String>,
+        /// Validity in days [default: configuration validity]
+        #[arg(long)]
+        validity: Option<u32>,
+        /// Additional DNS Subject Alternative Name, can be repeated
+        #[arg(long)]
+        dns: Vec<String>,
+        /// Additional IP address Subject Alternative Name, can be repeated
+        #[arg(long)]
+        ip: Vec<String>,
+        /// Additional email Subject Alternative Name, can be repeated
+        #[arg(long)]
+        email: Vec<String>,
+        /// Additional URI Subject Alternative Name, can be repeated
+        #[arg(long)]
+        uri: Vec<String>
+    },
+    /// Import the certificate an external issuer signed for the root authority of a saved PKI,
+    /// then issue the rest of its specification
+    ImportSigned {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Path of the PEM or DER certificate
+        #[arg(long)]
+        cert: PathBuf
+    },
+    /// Reissue a certificate of a saved PKI with its key, a new serial number and validity period
+    Renew {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Common name of the certificate to renew
+        #[arg(long)]
+        cn: String,
+        /// Validity in days [default: certificate or configuration validity]
+        #[arg(long)]
+        validity: Option<u32>
+    },
+    /// Reissue a certificate of a saved PKI with a new key
+    Rekey {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Common name of the certificate to re-key
+        #[arg(long)]
+        cn: String,
+        /// RSA key length [default: current key type]
+        #[arg(long, conflicts_with = "curve")]
+        keylen: Option<u32>,
+        /// Curve name: prime256v1, secp256k1, secp384r1, secp521r1, ed25519 or ed448, ec for prime256v1 [default: current key type]
+        #[arg(long)]
+        curve: Option<String>,
+        /// Validity in days [default: certificate or configuration validity]
+        #[arg(long)]
+        validity: Option<u32>,
+        /// Reissue the certificates issued by a re-keyed authority, instead of retiring its previous certificate
+        #[arg(long)]
+        reissue_subordinates: bool
+    },
+    /// Replace the root of a saved PKI, cross-signing the previous and new root keys
+    Rollover {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// RSA key length [default: current root key type]
+        #[arg(long, conflicts_with = "curve")]
+        keylen: Option<u32>,
+        /// Curve name: prime256v1, secp256k1, secp384r1, secp521r1, ed25519 or ed448, ec for prime256v1 [default: current root key type]
+        #[arg(long)]
+        curve: Option<String>,
+        /// Validity in days [default: root or configuration validity]
+        #[arg(long)]
+        validity: Option<u32>,
+        /// Reissue the certificates issued by the previous root under the new one
+        #[arg(long)]
+        reissue_subordinates: bool
+    },
+    /// List the certificates of a saved PKI
+    List {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String
+    },
+    /// Show a certificate of a saved PKI
+    Show {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Common name of the certificate
+        #[arg(long)]
+        cn: String
+    },
+    /// Verify the chain of every certificate of a saved PKI
+    Verify {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String
+    },
+    /// Revoke a certificate of a saved PKI
+    Revoke {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Common name of the certificate to revoke
+        #[arg(long, required_unless_present = "serial", conflicts_with = "serial")]
+        cn: Option<String>,
+        /// Hexadecimal serial number to revoke, instead of a common name
+        #[arg(long, requires = "issuer")]
+        serial: Option<String>,
+        /// Common name of the authority that issued the serial number
+        #[arg(long)]
+        issuer: Option<String>,
+        /// CRL reason code
+        #[arg(long, default_value = "unspecified")]
+        reason: RevocationReason
+    },
+    /// Generate the CRL of every authority of a saved PKI
+    Crl {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Only generate the CRL of this authority
+        #[arg(long)]
+        issuer: Option<String>,
+        /// Days until the next CRL update
+        #[arg(long, default_value_t = 30)]
+        next_update: u32
+    },
+    /// Answer OCSP requests for the authorities of a saved PKI
+    OcspServe {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Delegated OCSP signing certificate, used for its issuer, can be repeated
+        #[arg(long)]
+        responder: Vec<String>,
+        /// Days until the next status update
+        #[arg(long, default_value_t = 1)]
+        next_update: u32
+    },
+    /// Export certificates of a saved PKI as PKCS#12 bundles
+    Export {
+        /// Name of the saved PKI
+        #[arg(long)]
+        pki: String,
+        /// Only export this certificate
+        #[arg(long)]
+        cn: Option<String>,
+        /// Friendly name of the bundles [default: common name]
+        #[arg(long)]
+        friendly_name: Option<String>,
+        #[command(flatten)]
+        passphrase: PassphraseArgs
+    }
+}
 
-    init_from_env(
-        Env::new().default_filter_or("info")
+#[derive(ClapArgs, Debug)]
+#[group(multiple = false)]
+struct PassphraseArgs {
+    /// Passphrase protecting the output
+    #[arg(long)]
+    passphrase: Option<String>,
+    /// File holding the passphrase on its first line
+    #[arg(long)]
+    passphrase_file: Option<PathBuf>,
+    /// Environment variable holding the passphrase
+    #[arg(long)]
+    passphrase_env: Option<String>
+}
+
+#[derive(ClapArgs, Debug)]
+struct KeyPassphraseArgs {
+    /// Passphrase encrypting and decrypting the private keys
+    #[arg(long, global = true, conflicts_with_all = ["key_passphrase_file", "key_passphrase_env"])]
+    key_passphrase: Option<String>,
+    /// File holding the private keys passphrase on its first line
+    #[arg(long, global = true, conflicts_with = "key_passphrase_env")]
+    key_passphrase_file: Option<PathBuf>,
+    /// Environment variable holding the private keys passphrase
+    #[arg(long, global = true)]
+    key_passphrase_env: Option<String>,
+    /// Private keys to encrypt: authorities, certificates or all
+    #[arg(long, default_value = "all", global = true)]
+    encrypt_keys: EncryptedKeys
+}
+
+impl KeyPassphraseArgs {
+    fn source(self) -> Option<Passphrase> {
+        PassphraseArgs {
+            passphrase: self.key_passphrase,
+            passphrase_file: self.key_passphrase_file,
+            passphrase_env: self.key_passphrase_env
+        }.source()
+    }
+}
+
+fn parse_rdn(rdn: &str) -> Result<Vec<(String, String)>, String> {
+    rdn.split('+')
+        .map(|attribute| attribute.split_once('=')
+            .map(|(field, value)| (field.trim().to_string(), value.to_string()))
+            .filter(|(field, _)| !field.is_empty())
+            .ok_or_else(|| format!("expected FIELD=VALUE, got {:?}", attribute)))
+        .collect()
+}
+
+fn subject_from_rdns(rdns: Vec<Vec<(String, String)>>) -> Option<Subject> {
+    let mut subject = Subject::default();
+
+    for rdn in rdns {
+        match rdn.as_slice() {
+            [(field, value)] => subject.set(field, value),
+            _ => subject.rdns.push(rdn.into_iter().collect())
+        }
+    }
+
+    (subject != Subject::default()).then_some(subject)
+}
+
+/// Print a result, as JSON with `--json`
+fn print<T: Serialize>(value: &T, json: bool, human: impl FnOnce(&T)) {
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(value).unwrap_or_else(|err| {
+            error!("Cannot serialize result: {}", err);
+            exit(1);
+        })),
+        false => human(value)
+    }
+}
+
+fn print_revocation(revocation: &Revocation) {
+    println!(
+        "{} revoked ({}), serial {}",
+        revocation.cname.as_deref().unwrap_or("certificate"),
+        revocation.reason,
+        revocation.serial
     );
+}
+
+fn print_paths(paths: &Vec<PathBuf>) {
+    for path in paths {
+        println!("{}", path.display());
+    }
+}
 
+fn print_entries(entries: &Vec<EntryInfo>) {
+    for entry in entries {
+        let revoked = entry.revoked.map(|reason| format!(" revoked ({})", reason)).unwrap_or_default();
+
+        println!(
+            "{:<12} {:<24} {:<42} {}{}",
+            entry.kind,
+            entry.cname,
+            entry.serial.as_deref().unwrap_or("-"),
+            entry.not_after.as_deref().unwrap_or("-"),
+            revoked
+        );
+    }
+}
+
+impl PassphraseArgs {
+    fn source(self) -> Option<Passphrase> {
+        match (self.passphrase, self.passphrase_file, self.passphrase_env) {
+            (Some(value), _, _) => Some(Passphrase::Value(value)),
+            (_, Some(path), _) => Some(Passphrase::File(path)),
+            (_, _, Some(name)) => Some(Passphrase::Env(name)),
+            _ => None
+        }
+    }
+}
+
+pub fn main() {
     let args: Args = Args::parse();
 
+    if !args.json {
+        println!("{}", BANNER);
+    }
+
+    init_from_env(
+        Env::new().default_filter_or("info")
+    );
+
     let config_str: String = match args.configuration_file.is_empty() {
         true => DEFAULT_CONFIGURATION.to_string(),
         false => fs::read_to_string(args.configuration_file).expect("Cannot read configuration file")
@@ -45,7 +417,7 @@ pub fn main() ->  () {
     let configuration: Configuration = serde_json::from_str(&config_str)
         .unwrap_or_else(|err| {
              error!("Cannot parse configuration file: {}", err);
-             std::process::exit(1);
+             exit(1);
         });
 
     let mut manager: Pkimgr = Pkimgr::new(
@@ -53,13 +425,137 @@ pub fn main() ->  () {
         Path::new(&args.path).into()
     );
 
-    let pki_file: File = File::open(&args.pki_file).unwrap();
+    let encrypt_keys = args.key_passphrase.encrypt_keys;
 
-    info!("Using {} file to create PKI", args.pki_file);
+    if let Some(passphrase) = args.key_passphrase.source() {
+        manager.set_key_passphrase(&passphrase, encrypt_keys)
+            .unwrap_or_else(|err| {
+                error!("Cannot read private keys passphrase: {}", err);
+                exit(1);
+            });
+    }
 
-    manager.create_from_file(pki_file)
-        .unwrap_or_else(|err| {
-            error!("{}", err);
-            std::process::exit(1);
-        });
-}
\ No newline at end of file
+    let json = args.json;
+
+    let result = match args.command {
+        Command::Init { pki_file } => {
+            info!("Using {} file to create PKI", pki_file.display());
+
+            File::open(&pki_file)
+                .map_err(|err| err.into())
+                .and_then(|file| {
+                    // The manager only holds the PKI just created
+                    let pki = manager.create_from_file(file)?.get_pki()[0].to_owned();
+
+                    manager.list(&pki)
+                })
+                .map(|entries| print(&entries, json, print_entries))
+        },
+        Command::Issue {
+            pki, issuer, cn, keylen, curve, ca, profile, subject,
+            validity, not_before, not_after, outlive_issuer, digest, rsa_pss,
+            dns, ip, email, uri
+        } => {
+            let san = AltNames { dns, ip, email, uri };
+            let cert = Certificate {
+                cname: cn,
+                keylen,
+                curve,
+                ca,
+                profile,
+                subject: subject_from_rdns(subject),
+                validity,
+                not_before,
+                not_after,
+                outlive_issuer,
+                digest,
+                rsa_pss: rsa_pss.then_some(true),
+                san: (san != AltNames::default()).then_some(san),
+                ..Certificate::default()
+            };
+
+            let cname = cert.cname.to_owned();
+
+            manager.issue(&pki, &issuer, cert)
+                .map(|_| ())
+                .and_then(|_| manager.show(&pki, &cname))
+                .map(|entry| print(&entry, json, |entry| print!("{}", entry)))
+        },
+        Command::SignCsr { pki, issuer, csr, cn, profile, policy, allow_domain, validity, dns, ip, email, uri } => {
+            let san = AltNames { dns, ip, email, uri };
+            let cert = Certificate {
+                cname: cn.unwrap_or_default(),
+                profile,
+                validity,
+                san: (san != AltNames::default()).then_some(san),
+                ..Certificate::default()
+            };
+
+            manager.sign_csr(&pki, &issuer, &csr, cert, policy, &allow_domain)
+                .map(|entry| print(&entry, json, |entry| print!("{}", entry)))
+        },
+        Command::ImportSigned { pki, cert } => {
+            manager.import_signed(&pki, &cert)
+                .map(|_| ())
+                .and_then(|_| manager.list(&pki))
+                .map(|entries| print(&entries, json, print_entries))
+        },
+        Command::Revoke { pki, cn, serial, issuer, reason } => match (cn, serial, issuer) {
+            (Some(cn), _, _) => manager.revoke(&pki, &cn, reason).map(|revocation| print(&revocation, json, print_revocation)),
+            (_, Some(serial), Some(issuer)) => {
+                manager.revoke_serial(&pki, &issuer, &serial, reason).map(|revocation| print(&revocation, json, print_revocation))
+            },
+            _ => unreachable!("clap requires either --cn or --serial with --issuer")
+        },
+        Command::Crl { pki, issuer, next_update } => {
+            manager.generate_crl(&pki, issuer.as_ref(), next_update).map(|written| print(&written, json, print_paths))
+        },
+        Command::OcspServe { pki, listen, responder, next_update } => {
+            manager.ocsp_serve(&pki, &listen, responder, next_update).map(|_| ())
+        },
+        Command::Export { pki, cn, friendly_name, passphrase } => {
+            let passphrase = passphrase.source().unwrap_or_else(|| {
+                error!("A passphrase is required to export PKCS#12 bundles");
+                exit(1);
+            });
+
+            manager.export_pkcs12(&pki, cn.as_ref(), &passphrase, friendly_name.as_deref())
+                .map(|written| print(&written, json, print_paths))
+        },
+        Command::Renew { pki, cn, validity } => {
+            manager.renew(&pki, &cn, validity)
+                .map(|_| ())
+                .and_then(|_| manager.show(&pki, &cn))
+                .map(|entry| print(&entry, json, |entry| print!("{}", entry)))
+        },
+        Command::Rekey { pki, cn, keylen, curve, validity, reissue_subordinates } => {
+            manager.rekey(&pki, &cn, keylen, curve, validity, reissue_subordinates)
+                .map(|_| ())
+                .and_then(|_| manager.show(&pki, &cn))
+                .map(|entry| print(&entry, json, |entry| print!("{}", entry)))
+        },
+        Command::Rollover { pki, keylen, curve, validity, reissue_subordinates } => {
+            manager.rollover(&pki, keylen, curve, validity, reissue_subordinates)
+                .map(|_| ())
+                .and_then(|_| manager.list(&pki))
+                .map(|entries| print(&entries, json, print_entries))
+        },
+        Command::List { pki } => manager.list(&pki).map(|entries| print(&entries, json, print_entries)),
+        Command::Show { pki, cn } => manager.show(&pki, &cn).map(|entry| print(&entry, json, |entry| print!("{}", entry))),
+        Command::Verify { pki } => manager.verify(&pki).map(|failures| {
+            print(&failures, json, |failures| match failures.is_empty() {
+                true => println!("{}: every certificate chain is valid", pki),
+                false => failures.iter().for_each(|failure| println!("{}", failure))
+            });
+
+            if !failures.is_empty() {
+                exit(1);
+            }
+        })
+    };
+
+    result.unwrap_or_else(|err| {
+        error!("{}", err);
+        exit(1);
+    });
+}
@@ -0,0 +1,76 @@
+use core::fmt;
+use std::{env, fs, path::PathBuf, str::FromStr};
+
+use crate::error::ManagerError;
+
+
+/// Where to read a passphrase from
+#[derive(Clone, Debug)]
+pub enum Passphrase {
+    Value(String),
+    File(PathBuf),
+    Env(String),
+}
+
+
+impl Passphrase {
+    pub fn resolve(&self) -> Result<String, ManagerError> {
+        match self {
+            Passphrase::Value(value) => Ok(value.to_owned()),
+            Passphrase::File(path) => {
+                let content = fs::read_to_string(path)?;
+
+                // Only the first line, like openssl's file: pass phrase source
+                Ok(content.lines().next().unwrap_or_default().to_string())
+            },
+            Passphrase::Env(name) => env::var(name)
+                .map_err(|_| ManagerError::NotFound(format!("Environment variable {} is not set", name)))
+        }
+    }
+}
+
+
+/// Private keys to encrypt when saving a PKI
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum EncryptedKeys {
+    Authorities,
+    Certificates,
+    #[default]
+    All,
+}
+
+
+impl EncryptedKeys {
+    pub fn covers(&self, authority: bool) -> bool {
+        match self {
+            EncryptedKeys::Authorities => authority,
+            EncryptedKeys::Certificates => !authority,
+            EncryptedKeys::All => true,
+        }
+    }
+}
+
+
+impl FromStr for EncryptedKeys {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "authorities" => Ok(EncryptedKeys::Authorities),
+            "certificates" => Ok(EncryptedKeys::Certificates),
+            "all" => Ok(EncryptedKeys::All),
+            _ => Err(format!("Unknown key selection: {}", s))
+        }
+    }
+}
+
+
+impl fmt::Display for EncryptedKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptedKeys::Authorities => write!(f, "authorities"),
+            EncryptedKeys::Certificates => write!(f, "certificates"),
+            EncryptedKeys::All => write!(f, "all"),
+        }
+    }
+}
@@ -1,22 +1,66 @@
 use core::fmt;
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::{BTreeMap, BTreeSet, HashMap, HashSet}, mem};
 
 use log::error;
-use openssl::x509::X509;
+use openssl::{
+    asn1::{Asn1Time, Asn1TimeRef},
+    bn::BigNum,
+    error::ErrorStack,
+    pkcs12::Pkcs12,
+    pkey::{PKey, Public},
+    stack::Stack,
+    x509::{
+        store::X509StoreBuilder,
+        verify::X509VerifyFlags,
+        X509Crl,
+        X509Req,
+        X509StoreContext,
+        X509Ref,
+        X509
+    }
+};
 use serde::{Serialize, Deserialize};
 use serde_json;
 
 use crate::{
     certificates::{
-        x509::{create_x509_leaf, create_x509_node, x509_to_certificate},
+        access::AccessUrls,
+        chain::{check_issued, check_path_length, check_validity, is_ca},
+        crl::{create_crl, CrlArgs, Revocation, RevocationList, RevocationReason},
+        csr::{check_alt_names, requested_names, CsrPolicy},
+        ocsp::{CertId, CertStatus},
+        policies::Policy,
+        serial::SerialIndex,
+        signature::{Digest, Signature},
+        x509::{create_x509_leaf, create_x509_node, create_x509_req, parse_time, x509_to_certificate},
+        AltNames,
         CertArgs,
-        Certificate
-    }, Configuration, error::PKIError, key::Key
+        Certificate,
+        KeyIdentifier,
+        PreviousCertificate,
+        Subject
+    },
+    Configuration,
+    error::PKIError,
+    key::Key,
+    report::{EntryInfo, EntryKind, VerifyFailure}
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PkiJSON {
     pub pki_name: String,
+    /// Signature digest, by default the one matching the signing key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<Digest>,
+    /// Sign with RSASSA-PSS when the signing key is RSA
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rsa_pss: bool,
+    /// Method of the key identifiers, SHA-1 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_identifier: Option<KeyIdentifier>,
+    /// CRL, OCSP and issuer URLs of every certificate, unless their authority overrides them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_urls: Option<AccessUrls>,
     pub root: Certificate
 }
 
@@ -34,14 +78,53 @@ impl fmt::Display for PkiJSON {
     }
 }
 
+/// Certificates of a root rollover. Both roots share their name, each key is
+/// certified by the other one for clients trusting either root.
+pub struct Rollover {
+    pub old_root: X509,
+    pub new_root: X509,
+    /// Previous root key signed by the new one
+    pub old_with_new: X509,
+    /// New root key signed by the previous one
+    pub new_with_old: X509,
+}
+
+/// Authority certificate an OCSP certificate identifier designates, with the key signing
+/// the response: the current one, or a retired one for certificates issued before a re-key
+pub struct OcspIssuer<'a> {
+    pub name: &'a String,
+    pub cert: &'a X509,
+    pub key: &'a Key,
+    pub retired: bool,
+}
+
+impl OcspIssuer<'_> {
+    /// Whether both designate the same authority certificate, for responses signed by one key
+    pub fn same(&self, other: &OcspIssuer) -> bool {
+        self.name == other.name && std::ptr::eq(self.cert, other.cert)
+    }
+}
+
 #[derive(Clone)]
 pub struct Pki {
     pub name: String,
     pub authorities: HashMap<String, (X509, Key)>,
     pub certs: HashMap<String, (X509, Key)>,
+    /// Certificates signed from a CSR, whose private key is held by their requester
+    pub external: HashMap<String, X509>,
+    /// Root authority waiting for its certificate from an external issuer, with its PEM request
+    pub pending: HashMap<String, (Vec<u8>, Key)>,
+    /// Authority certificates replaced by a re-key, with their key, by common name, which
+    /// entries issued before and not reissued still chain to
+    pub retired: HashMap<String, Vec<(X509, Key)>>,
     // path: PathBuf,
     pub json: PkiJSON,
-    configuration: Configuration
+    /// Revoked certificates, by issuing authority
+    pub revocations: BTreeMap<String, RevocationList>,
+    /// Serial numbers used, by issuing authority
+    pub serials: BTreeMap<String, SerialIndex>,
+    configuration: Configuration,
+    modified: HashSet<String>
 }
 
 impl Pki {
@@ -50,111 +133,1258 @@ impl Pki {
             name: pki_name.into(),
             authorities: HashMap::new(),
             certs: HashMap::new(),
+            external: HashMap::new(),
+            pending: HashMap::new(),
+            retired: HashMap::new(),
             json: PkiJSON {
                 pki_name: pki_name.into(),
+                digest: None,
+                rsa_pss: false,
+                key_identifier: None,
+                access_urls: None,
                 root: Certificate::default()
             },
-            configuration
+            revocations: BTreeMap::new(),
+            serials: BTreeMap::new(),
+            configuration,
+            modified: HashSet::new()
         }
     }
 
 
-    pub fn load(json: PkiJSON, configuration: Configuration) -> Pki {
-        // TODO Load existing PKI here
-        // must be removed
-        Pki::new(&json.pki_name, configuration)
+    /// Rebuild a PKI from its metadata and the certificates and keys found on disk,
+    /// indexed by common name, along with its retired authority certificates and keys.
+    /// Every entry must match the metadata tree.
+    pub fn load(
+        json: PkiJSON,
+        configuration: Configuration,
+        mut entries: HashMap<String, (X509, Option<Key>)>,
+        retired: HashMap<String, Vec<(X509, Key)>>
+    ) -> Result<Pki, PKIError> {
+        let mut pki = Pki::new(&json.pki_name, configuration);
+
+        for (name, (cert, key)) in retired.iter().flat_map(|(name, retired)| retired.iter().map(move |entry| (name, entry))) {
+            let pkey = key.to_private_pkey()?;
+
+            if !cert.public_key()?.public_eq(&pkey) {
+                return Err(PKIError::Inconsistent(
+                    format!("{} on {}: private key does not match its retired certificate", name, pki.name)
+                ));
+            }
+        }
+
+        pki.retired = retired;
+
+        pki.load_entry(&json.root, None, &mut entries)?;
+
+        if let Some(name) = entries.keys().next() {
+            return Err(PKIError::Inconsistent(
+                format!("{} is not described in {} metadata", name, pki.name)
+            ));
+        }
+
+        pki.json = json;
+
+        Ok(pki)
+    }
+
+
+    /// Rebuild a PKI whose root authority still waits for its externally signed certificate.
+    /// `json` root holds the specification of the sub certificates to issue once imported.
+    pub fn load_pending(json: PkiJSON, configuration: Configuration, req: X509Req, key: Key) -> Result<Pki, PKIError> {
+        let mut pki = Pki::new(&json.pki_name, configuration);
+
+        let pkey = key.to_private_pkey()?;
+
+        if !req.public_key()?.public_eq(&pkey) {
+            return Err(PKIError::Inconsistent(
+                format!("{} on {}: private key does not match its request", json.root.cname, pki.name)
+            ));
+        }
+
+        pki.pending.insert(json.root.cname.to_owned(), (req.to_pem()?, key));
+        pki.json = json;
+
+        Ok(pki)
     }
 
 
-    pub fn get_configuration(self: &Self) -> Configuration {
+    pub fn get_configuration(&self) -> Configuration {
         self.configuration.clone()
     }
 
 
-    pub fn add_authority(self: &mut Self, name: &String, auth_name: Option<&String>, key: Key) -> Result<&Self, PKIError> {
-        let (authority_issuer, authority_pkey) = match auth_name {
-            Some(name) => {
-                let (cert, key) = self.find_authority(name)?;
+    /// Certificate and key of an authority or a certificate of this PKI.
+    pub fn get_entry(&self, name: &String) -> Result<&(X509, Key), PKIError> {
+        self.authorities.get(name)
+            .or_else(|| self.certs.get(name))
+            .ok_or_else(|| PKIError::NotFound(format!("{} not found on {}", name, self.name)))
+    }
 
-                (Some(cert.subject_name().to_owned()?), Some(key.to_owned()))
-            },
-            None => (None, None)
-        };
 
-        let cert = create_x509_node(
-            CertArgs {
-                authority_issuer,
-                authority_pkey,
-                key: key.clone(),
-                name: name.to_owned(),
-                cert_entries: self.configuration.x509_certs_entries.clone()
+    /// Certificate of any entry of this PKI, including the ones without private key.
+    pub fn get_cert(&self, name: &String) -> Result<&X509, PKIError> {
+        match self.external.get(name) {
+            Some(cert) => Ok(cert),
+            None => self.get_entry(name).map(|(cert, _)| cert)
+        }
+    }
+
+
+    /// Common name of the authority that issued an entry, `None` for the root.
+    pub fn get_issuer(&self, name: &String) -> Option<&String> {
+        find_issuer(&self.json.root, name)
+    }
+
+
+    /// Names of the entries added or changed since the last call, which still need to be written.
+    pub fn take_modified(&mut self) -> HashSet<String> {
+        mem::take(&mut self.modified)
+    }
+
+
+    /// Record the serial numbers of the loaded certificates, retired ones and metadata
+    /// history in the index of their issuer, for PKIs saved without it. Two entries of an
+    /// authority sharing a serial number make the PKI inconsistent.
+    pub fn index_serials(&mut self) -> Result<(), PKIError> {
+        let mut names = vec![];
+        let mut found: BTreeMap<String, SerialIndex> = BTreeMap::new();
+
+        collect_names(&self.json.root, &mut names);
+
+        for name in names {
+            // The serial number of an externally signed root is not drawn by this PKI
+            let issuer = match self.get_issuer(name) {
+                Some(issuer) => issuer,
+                None if self.json.root.external_issuer => continue,
+                None => name
+            };
+            // A retired certificate is also in the history of its entry
+            let mut serials = BTreeSet::new();
+
+            let retired = self.retired.get(name).into_iter().flatten().map(|(cert, _)| cert);
+
+            for cert in self.get_cert(name).ok().into_iter().chain(retired) {
+                serials.insert(cert.serial_number().to_bn()?.to_hex_str()?.to_string());
+            }
+
+            for previous in find_entry(&self.json.root, name).into_iter().flat_map(|entry| entry.history.iter()) {
+                serials.insert(BigNum::from_hex_str(&previous.serial)?.to_hex_str()?.to_string());
             }
-        )?;
 
-        if auth_name.is_none() {
-            self.json.root = x509_to_certificate(&cert, &key).clone();
-        } else {
-            serialize(
-                &mut self.json.root,
-                &x509_to_certificate(&cert, &key),
-                &name,
-                &auth_name.unwrap_or(&name),
-                &key
-            );
+            for serial in serials {
+                if !found.entry(issuer.to_owned()).or_default().record(&BigNum::from_hex_str(&serial)?)? {
+                    return Err(PKIError::Inconsistent(
+                        format!("{} on {}: serial number {} is already used by {}", name, self.name, serial, issuer)
+                    ));
+                }
+            }
+        }
+
+        for (issuer, index) in found {
+            self.serials.entry(issuer).or_default().merge(index);
+        }
+
+        Ok(())
+    }
+
+
+    pub fn add_authority(&mut self, spec: &Certificate, auth_name: Option<&String>, key: Key) -> Result<&Self, PKIError> {
+        let name = &spec.cname;
+
+        self.check_available(name)?;
+
+        if spec.external_issuer {
+            return self.add_pending_authority(spec, auth_name, key);
+        }
+
+        let cert = create_x509_node(self.cert_args(spec, auth_name, key.to_public_pkey()?, Some(&key))?)?;
+
+        if let Some(auth_name) = auth_name {
+            self.check_constraints(&cert, auth_name)?;
+        }
+
+        self.record_serial(auth_name.unwrap_or(name), &cert)?;
+
+        let entry = Certificate {
+            ca: true,
+            ..metadata_entry(spec, &cert)?
+        };
+
+        match auth_name {
+            None => self.json.root = entry,
+            Some(auth_name) => {
+                serialize(&mut self.json.root, &entry, auth_name);
+            }
         }
 
         self.authorities.insert(name.to_owned(), (cert.clone(), key));
+        self.modified.insert(name.to_owned());
 
         Ok(self)
     }
 
 
-    pub fn add_certificate(self: &mut Self, name: &String, auth_name: &String, key: Key) -> Result<&Self, PKIError> {
-        let (issuer_cert, issuer_key) = self.find_authority(auth_name)?;
+    pub fn add_certificate(&mut self, spec: &Certificate, auth_name: &String, key: Key) -> Result<&Self, PKIError> {
+        let name = &spec.cname;
 
-        let cert = create_x509_leaf(
-            CertArgs {
-                authority_issuer: Some(issuer_cert.subject_name().to_owned()?),
-                authority_pkey: Some(issuer_key.to_owned()),
-                key: key.clone(),
-                name: name.to_owned(),
-                cert_entries: self.configuration.x509_certs_entries.clone()
-            }
-        )?;
+        self.check_available(name)?;
 
-        serialize(
-            &mut self.json.root,
-            &x509_to_certificate(&cert, &key),
-            &name,
-            &auth_name,
-            &key
-        );
+        let cert = create_x509_leaf(self.cert_args(spec, Some(auth_name), key.to_public_pkey()?, Some(&key))?)?;
+
+        self.check_constraints(&cert, auth_name)?;
+        self.record_serial(auth_name, &cert)?;
+
+        let entry = Certificate {
+            profile: Some(spec.profile.unwrap_or_default()),
+            ..metadata_entry(spec, &cert)?
+        };
+
+        serialize(&mut self.json.root, &entry, auth_name);
 
         self.certs.insert(name.to_owned(), (cert.clone(), key));
+        self.modified.insert(name.to_owned());
 
         Ok(self)
 
     }
 
 
+    /// Install the certificate returned by the external issuer of the pending root authority.
+    /// Returns the specification of its sub certificates, which can now be issued.
+    pub fn import_signed(&mut self, cert: X509) -> Result<Vec<Certificate>, PKIError> {
+        let name = self.json.root.cname.to_owned();
+        let (_, key) = self.pending.get(&name)
+            .ok_or_else(|| PKIError::NotFound(format!("{} has no authority waiting for a certificate", self.name)))?;
+
+        let pkey = key.to_private_pkey()?;
+
+        if !cert.public_key()?.public_eq(&pkey) {
+            return Err(PKIError::Inconsistent(format!("{} certificate does not match the key of {}", self.name, name)));
+        }
+
+        if x509_to_certificate(&cert)?.cname != name {
+            return Err(PKIError::Inconsistent(format!("{} certificate is not issued to {}", self.name, name)));
+        }
+
+        let (_, key) = self.pending.remove(&name).ok_or_else(|| PKIError::NotFound(name.to_owned()))?;
+        let spec = mem::take(&mut self.json.root);
+
+        self.json.root = Certificate {
+            ca: true,
+            ..metadata_entry(&spec, &cert)?
+        };
+
+        self.authorities.insert(name.to_owned(), (cert, key));
+        self.modified.insert(name);
+
+        Ok(spec.subcerts)
+    }
+
+
+    /// Sign an external PKCS#10 request with an authority. The requested names allowed by
+    /// `policy` are copied, `spec` provides the common name when the request has none, and
+    /// the rest of the certificate settings. Requested alternative names must be in `spec` or
+    /// within `allowed_domains`. The entry is recorded without private key, its common name
+    /// is returned.
+    pub fn sign_csr(
+        &mut self,
+        spec: &Certificate,
+        auth_name: &String,
+        req: &X509Req,
+        policy: CsrPolicy,
+        allowed_domains: &[String]
+    ) -> Result<String, PKIError> {
+        let public_key = req.public_key()?;
+
+        if !req.verify(&public_key)? {
+            return Err(PKIError::InvalidSpec("CSR signature does not verify".to_string()));
+        }
+
+        let names = policy.apply(requested_names(req)?);
+
+        let cname = match (spec.cname.is_empty(), names.common_name) {
+            (false, _) => spec.cname.to_owned(),
+            (true, Some(cname)) => cname,
+            (true, None) => return Err(PKIError::InvalidSpec("CSR has no common name, one is required".to_string()))
+        };
+
+        let configured = spec.san.clone().unwrap_or_default();
+
+        check_alt_names(&names.san, &configured, allowed_domains).map_err(PKIError::InvalidSpec)?;
+
+        // The configured names the request does not already hold come after the requested ones
+        let mut san = names.san;
+        let extend = |names: &mut Vec<String>, extra: &Vec<String>| {
+            let missing: Vec<String> = extra.iter().filter(|name| !names.contains(name)).cloned().collect();
+            names.extend(missing);
+        };
+
+        extend(&mut san.dns, &configured.dns);
+        extend(&mut san.ip, &configured.ip);
+        extend(&mut san.email, &configured.email);
+        extend(&mut san.uri, &configured.uri);
+
+        let spec = Certificate {
+            cname,
+            subject: spec.subject.clone().or((names.subject != Subject::default()).then_some(names.subject)),
+            san: (san != AltNames::default()).then_some(san),
+            external_key: true,
+            ..spec.clone()
+        };
+
+        spec.validate().map_err(PKIError::InvalidSpec)?;
+        self.check_available(&spec.cname)?;
+
+        let cert = create_x509_leaf(self.cert_args(&spec, Some(auth_name), public_key, None)?)?;
+
+        self.check_constraints(&cert, auth_name)?;
+        self.record_serial(auth_name, &cert)?;
+
+        let entry = Certificate {
+            profile: Some(spec.profile.unwrap_or_default()),
+            ..metadata_entry(&spec, &cert)?
+        };
+
+        serialize(&mut self.json.root, &entry, auth_name);
+
+        self.external.insert(spec.cname.to_owned(), cert);
+        self.modified.insert(spec.cname.to_owned());
+
+        Ok(spec.cname)
+    }
+
+
+    /// Reissue an entry with its key and settings, a new serial number and a new validity
+    /// period, of `validity` days when set. Certificates it issued remain valid.
+    pub fn renew(&mut self, name: &String, validity: Option<u32>) -> Result<&Self, PKIError> {
+        self.reissue(name, validity, None)?;
+
+        Ok(self)
+    }
+
+
+    /// Reissue an entry with a new key. Certificates issued by a re-keyed authority are
+    /// reissued too with `reissue_subordinates`, otherwise they keep chaining to its
+    /// previous certificate, which is retired.
+    pub fn rekey(
+        &mut self,
+        name: &String,
+        key: Key,
+        validity: Option<u32>,
+        reissue_subordinates: bool
+    ) -> Result<&Self, PKIError> {
+        if self.external.contains_key(name) {
+            return Err(PKIError::InvalidSpec(
+                format!("{} private key is held by its requester, sign a new CSR instead", name)
+            ));
+        }
+
+        let previous_key = self.authorities.get(name).map(|(_, key)| key.clone());
+        let previous = self.reissue(name, validity, Some(key))?;
+        let subordinates: Vec<String> = find_entry(&self.json.root, name)
+            .map(|entry| entry.subcerts.iter().map(|subcert| subcert.cname.to_owned()).collect())
+            .unwrap_or_default();
+
+        match reissue_subordinates {
+            // Their own keys are kept, so the certificates they issued remain valid
+            true => for subordinate in subordinates.iter() {
+                self.reissue(subordinate, None, None)?;
+                self.reissue_retired(subordinate)?;
+            },
+            // The previous key still signs CRLs and OCSP responses for them
+            false if !subordinates.is_empty() => if let Some(previous_key) = previous_key {
+                self.retired.entry(name.to_owned()).or_default().push((previous, previous_key));
+            },
+            false => ()
+        }
+
+        Ok(self)
+    }
+
+
+    /// Replace the root key and certificate, and cross-sign the previous and new root keys.
+    /// The previous root is retired so that certificates it issued remain valid until they
+    /// are reissued, at once with `reissue_subordinates` or later on by renewing them.
+    pub fn rollover(&mut self, key: Key, validity: Option<u32>, reissue_subordinates: bool) -> Result<Rollover, PKIError> {
+        let root = self.json.root.cname.to_owned();
+        let (old_root, old_key) = self.find_authority(&root)?.clone();
+        let retired_before = self.retired.get(&root).map_or(0, Vec::len);
+
+        self.rekey(&root, key, validity, reissue_subordinates)?;
+
+        // Clients keep trusting the previous root until they get the new one
+        let retired = self.retired.entry(root.to_owned()).or_default();
+        if retired.len() == retired_before {
+            retired.push((old_root.clone(), old_key.clone()));
+        }
+
+        let (new_root, new_key) = self.find_authority(&root)?.clone();
+        let spec = find_entry(&self.json.root, &root)
+            .map(|entry| Certificate { not_before: None, not_after: None, subcerts: vec![], ..entry.clone() })
+            .ok_or_else(|| PKIError::NotFound(format!("{} not found on {}", root, self.name)))?;
+
+        Ok(Rollover {
+            old_with_new: self.cross_certificate(&spec, &old_root, (&new_root, &new_key), &old_root)?,
+            new_with_old: self.cross_certificate(&spec, &new_root, (&old_root, &old_key), &old_root)?,
+            old_root,
+            new_root
+        })
+    }
+
+
+    /// Revoke a certificate of this PKI against its issuing authority.
+    pub fn revoke(&mut self, name: &String, reason: RevocationReason) -> Result<Revocation, PKIError> {
+        let cert = self.get_cert(name)?;
+        let serial = cert.serial_number().to_bn()?.to_hex_str()?.to_string();
+
+        let auth_name = self.get_issuer(name)
+            .ok_or_else(|| PKIError::NotFound(format!("{} has no issuer on {}", name, self.name)))?
+            .to_owned();
+
+        self.revoke_serial(&auth_name, &serial, Some(name.to_owned()), reason)
+    }
+
+
+    /// Record a revoked serial number against an authority, returning the recorded revocation.
+    pub fn revoke_serial(
+        &mut self,
+        auth_name: &String,
+        serial: &str,
+        cname: Option<String>,
+        reason: RevocationReason
+    ) -> Result<Revocation, PKIError> {
+        self.find_authority(auth_name)?;
+
+        let revocation = Revocation::new(serial, cname, reason)?;
+        let list = self.revocations.entry(auth_name.to_owned()).or_default();
+
+        if list.revoked.iter().any(|revoked| revoked.serial == revocation.serial) {
+            return Err(PKIError::AlreadyExists(
+                format!("{} is already revoked by {}", revocation.serial, auth_name)
+            ));
+        }
+
+        list.revoked.push(revocation.clone());
+
+        Ok(revocation)
+    }
+
+
+    /// Sign a new CRL for an authority, bumping its CRL number.
+    pub fn crl(&mut self, auth_name: &String, next_update: u32) -> Result<X509Crl, PKIError> {
+        let (issuer, issuer_key) = self.find_authority(auth_name)?.clone();
+
+        self.sign_crl(auth_name, &issuer, &issuer_key, next_update)
+    }
+
+
+    /// Sign a new CRL with every retired key of an authority whose certificate is still valid,
+    /// for the certificates issued before its re-key. Indexed by retired certificate serial number.
+    pub fn retired_crls(&mut self, auth_name: &String, next_update: u32) -> Result<Vec<(String, X509Crl)>, PKIError> {
+        let now = Asn1Time::days_from_now(0)?;
+        let retired: Vec<(X509, Key)> = self.retired.get(auth_name).into_iter().flatten()
+            .filter(|(cert, _)| cert.not_after() > now)
+            .cloned()
+            .collect();
+        let mut crls = vec![];
+
+        for (issuer, issuer_key) in retired {
+            let serial = issuer.serial_number().to_bn()?.to_hex_str()?.to_string();
+
+            crls.push((serial, self.sign_crl(auth_name, &issuer, &issuer_key, next_update)?));
+        }
+
+        Ok(crls)
+    }
+
+
+    /// Sign the revocation list of an authority with one of its certificates and keys. The CRL
+    /// number is shared by all of them, each CRL gets a new one.
+    fn sign_crl(&mut self, auth_name: &String, issuer: &X509, issuer_key: &Key, next_update: u32) -> Result<X509Crl, PKIError> {
+        let signature = self.signature(issuer_key);
+        let authority_key_id = self.key_id(issuer)?;
+        let list = self.revocations.entry(auth_name.to_owned()).or_default();
+
+        list.crl_number += 1;
+
+        Ok(create_crl(CrlArgs {
+            issuer,
+            issuer_key,
+            authority_key_id: &authority_key_id,
+            crl_number: list.crl_number,
+            next_update,
+            revoked: &list.revoked,
+            signature
+        })?)
+    }
+
+
+    /// Issuer certificates of an entry, from its direct issuer up to the root.
+    pub fn get_chain(&self, name: &String) -> Result<Vec<X509>, PKIError> {
+        let mut chain = vec![];
+        let mut current = name;
+
+        let mut cert = self.get_cert(name)?;
+
+        while let Some(auth_name) = self.get_issuer(current) {
+            cert = self.issuer_cert(cert, auth_name)?;
+            chain.push(cert.clone());
+            current = auth_name;
+        }
+
+        Ok(chain)
+    }
+
+
+    /// DER encoded PKCS#12 bundle of an entry key, certificate and issuer chain.
+    pub fn pkcs12(&self, name: &String, passphrase: &str, friendly_name: Option<&str>) -> Result<Vec<u8>, PKIError> {
+        if self.external.contains_key(name) {
+            return Err(PKIError::NotFound(format!("{} private key is not held on {}", name, self.name)));
+        }
+
+        let (cert, key) = self.get_entry(name)?;
+        let pkey = key.to_private_pkey()?;
+        let mut chain = Stack::new()?;
+
+        for issuer in self.get_chain(name)? {
+            chain.push(issuer)?;
+        }
+
+        let pkcs12 = Pkcs12::builder()
+            .name(friendly_name.unwrap_or(name))
+            .pkey(&pkey)
+            .cert(cert)
+            .ca(chain)
+            .build2(passphrase)?;
+
+        Ok(pkcs12.to_der()?)
+    }
+
+
+    /// Authority certificate designated by an OCSP certificate identifier, current or retired,
+    /// and the status of the certificate against it. `None` when no authority of this PKI matches.
+    /// Serial numbers the authority issued and did not revoke are good, renewed certificates included.
+    pub fn ocsp_status(&self, id: &CertId) -> Result<Option<(OcspIssuer<'_>, CertStatus)>, PKIError> {
+        for (auth_name, current) in self.authorities.iter() {
+            let retired = self.retired.get(auth_name).into_iter().flatten().map(|entry| (entry, true));
+            let mut issuer = None;
+
+            for ((cert, key), retired) in std::iter::once((current, false)).chain(retired) {
+                if id.is_issued_by(cert)? {
+                    issuer = Some(OcspIssuer { name: auth_name, cert, key, retired });
+                    break;
+                }
+            }
+
+            let Some(issuer) = issuer else {
+                continue;
+            };
+
+            let serial = id.serial.to_hex_str()?.to_string();
+
+            let revoked = self.revocations.get(auth_name)
+                .and_then(|list| list.revoked.iter().find(|revocation| revocation.serial == serial));
+
+            if let Some(revocation) = revoked {
+                return Ok(Some((issuer, CertStatus::Revoked { date: revocation.date, reason: revocation.reason })));
+            }
+
+            let issued = self.serials.get(auth_name).is_some_and(|index| index.issued.contains(&serial));
+
+            return Ok(Some((issuer, if issued { CertStatus::Good } else { CertStatus::Unknown })));
+        }
+
+        Ok(None)
+    }
+
+
+    /// Summary of every entry, in metadata order from the root.
+    pub fn entries(&self) -> Result<Vec<EntryInfo>, PKIError> {
+        let root = &self.json.root;
+
+        // The sub certificates of a pending root are not issued yet
+        if self.pending.contains_key(&root.cname) {
+            return Ok(vec![EntryInfo::pending(&root.cname)]);
+        }
+
+        let mut names = vec![];
+        collect_names(root, &mut names);
+
+        names.into_iter().map(|name| self.entry_info(name)).collect()
+    }
+
+
+    /// Summary of an entry, with its revocation status.
+    pub fn entry_info(&self, name: &String) -> Result<EntryInfo, PKIError> {
+        if self.pending.contains_key(name) {
+            return Ok(EntryInfo::pending(name));
+        }
+
+        let cert = self.get_cert(name)?;
+        let issuer = self.get_issuer(name);
+        let kind = match issuer {
+            _ if self.external.contains_key(name) => EntryKind::External,
+            None => EntryKind::Root,
+            Some(_) if self.authorities.contains_key(name) => EntryKind::Authority,
+            Some(_) => EntryKind::Certificate
+        };
+
+        let mut info = EntryInfo::from_x509(cert, kind, issuer)?;
+
+        if let Some(entry) = find_entry(&self.json.root, name) {
+            info.profile = entry.profile;
+            info.history = entry.history.clone();
+        }
+
+        info.revoked = issuer
+            .and_then(|auth_name| self.revocations.get(auth_name))
+            .and_then(|list| list.revoked.iter().find(|revocation| Some(&revocation.serial) == info.serial.as_ref()))
+            .map(|revocation| revocation.reason);
+
+        Ok(info)
+    }
+
+
+    /// Verify the chain of every entry up to the root with openssl, which covers
+    /// signatures, validity periods and basic constraints. Revocations are not checked.
+    pub fn verify(&self) -> Result<Vec<VerifyFailure>, PKIError> {
+        let root = &self.json.root.cname;
+
+        if self.pending.contains_key(root) {
+            return Ok(vec![VerifyFailure {
+                cname: root.to_owned(),
+                reason: "waiting for its externally signed certificate".to_string()
+            }]);
+        }
+
+        let mut failures = vec![];
+
+        for info in self.entries()? {
+            let cert = self.get_cert(&info.cname)?;
+            let mut chain = self.get_chain(&info.cname)?;
+            let mut problems = check_validity(cert)?;
+
+            match chain.first() {
+                Some(issuer) => problems.extend(check_issued(cert, issuer)?),
+                None if !self.json.root.external_issuer => problems.extend(check_issued(cert, cert)?),
+                // Only the external issuer can check an externally signed root
+                None => ()
+            }
+
+            let authority = matches!(info.kind, EntryKind::Root | EntryKind::Authority);
+
+            if authority && !is_ca(cert) {
+                problems.push("authority is not a CA".to_string());
+            } else if !authority && is_ca(cert) {
+                problems.push("end entity certificate is a CA".to_string());
+            }
+
+            let path: Vec<&X509Ref> = [cert.as_ref()].into_iter().chain(chain.iter().map(|issuer| issuer.as_ref())).collect();
+            problems.extend(check_path_length(&path)?);
+
+            // Openssl only reports its first error, so it runs once the explicit checks pass
+            if problems.is_empty() {
+                // The root that signed this chain, several ones share their name after a rollover
+                let anchor = chain.pop().unwrap_or_else(|| cert.clone());
+
+                let mut store = X509StoreBuilder::new()?;
+                store.add_cert(anchor)?;
+                // An externally signed root is trusted without its own issuers
+                store.set_flags(X509VerifyFlags::PARTIAL_CHAIN)?;
+                let store = store.build();
+
+                let mut untrusted = Stack::new()?;
+
+                for issuer in chain {
+                    untrusted.push(issuer)?;
+                }
+
+                let mut context = X509StoreContext::new()?;
+                let error = context.init(&store, cert, &untrusted, |context| {
+                    Ok((!context.verify_cert()?).then(|| context.error().error_string().to_string()))
+                })?;
+
+                problems.extend(error);
+            }
+
+            failures.extend(problems.into_iter().map(|reason| VerifyFailure { cname: info.cname.clone(), reason }));
+        }
+
+        Ok(failures)
+    }
+
+
+    /// Signature made by `key` for this PKI: its digest and padding settings, or the key defaults.
+    pub fn signature(&self, key: &Key) -> Signature {
+        Signature {
+            digest: self.json.digest.unwrap_or(Signature::for_key(key).digest),
+            rsa_pss: self.json.rsa_pss
+        }
+    }
+
+
     // Privates
-    fn find_authority(self: &mut Self, name: &String) -> Result<&(X509, Key), PKIError> {
+    fn find_authority(&self, name: &String) -> Result<&(X509, Key), PKIError> {
         self.authorities.get(name)
             .ok_or_else(|| PKIError::NotFound(format!("{} not found on {}", name, self.name)))
     }
+
+
+    /// Arguments to sign `public_key` with an authority, or with `key` when self-signed.
+    /// The serial number is reserved, the certificate records it once issued.
+    fn cert_args(
+        &self,
+        spec: &Certificate,
+        auth_name: Option<&String>,
+        public_key: PKey<Public>,
+        key: Option<&Key>
+    ) -> Result<CertArgs, PKIError> {
+        let issuer = auth_name.unwrap_or(&spec.cname);
+        let strategy = match auth_name {
+            Some(name) => find_entry(&self.json.root, name).and_then(|entry| entry.serials),
+            None => spec.serials
+        };
+        let serial = match self.serials.get(issuer) {
+            Some(index) => index.next(strategy.unwrap_or_default())?,
+            None => SerialIndex::default().next(strategy.unwrap_or_default())?
+        };
+        let authority = auth_name.map(|name| self.find_authority(name)).transpose()?;
+        let (authority_issuer, authority_pkey) = match (authority, key) {
+            (Some((cert, key)), _) => (Some(cert.subject_name().to_owned()?), key.to_owned()),
+            (None, Some(key)) => (None, key.to_owned()),
+            (None, None) => return Err(PKIError::NotFound(format!("{} has no key to self-sign", spec.cname)))
+        };
+        let default_signature = self.signature(&authority_pkey);
+        let signature = Signature {
+            digest: spec.digest.unwrap_or(default_signature.digest),
+            rsa_pss: spec.rsa_pss.unwrap_or(default_signature.rsa_pss)
+        };
+        let (not_before, not_after) = self.validity(spec, authority.map(|(cert, _)| cert))?;
+        let key_identifier = self.json.key_identifier.unwrap_or_default();
+        let subject_key_id = key_identifier.compute(&public_key)?;
+        let signing_key = authority_pkey.to_public_pkey()?;
+        let authority_key_id = match authority {
+            Some((cert, _)) => Some(self.key_id(cert)?),
+            // Cross-certificates are signed by another key than their own
+            None if !signing_key.public_eq(&public_key) => Some(key_identifier.compute(&signing_key)?),
+            None => None
+        };
+
+        let subject = spec.subject.clone().unwrap_or_default().resolve(&self.configuration.x509_certs_entries);
+
+        subject.validate()
+            .map_err(|err| PKIError::InvalidSpec(format!("{} subject: {}", spec.cname, err)))?;
+
+        Ok(CertArgs {
+            authority_issuer,
+            authority_pkey,
+            public_key,
+            name: spec.cname.to_owned(),
+            subject,
+            not_before,
+            not_after,
+            signature,
+            subject_key_id,
+            authority_key_id,
+            san: spec.san.clone(),
+            profile: spec.profile.unwrap_or_default(),
+            serial,
+            path_len: spec.path_len,
+            name_constraints: spec.name_constraints.clone(),
+            access_urls: auth_name.map(|name| self.access_urls(name)).unwrap_or_default(),
+            policies: self.policies(spec, auth_name),
+            policy_constraints: spec.policy_constraints,
+            inhibit_any_policy: spec.inhibit_any_policy,
+            extensions: spec.extensions.clone()
+        })
+    }
+
+
+    /// URLs of the certificates issued by an authority: its own, the closest ones above it, or the PKI ones
+    fn access_urls(&self, auth_name: &String) -> AccessUrls {
+        let mut current = Some(auth_name);
+
+        while let Some(name) = current {
+            if let Some(access_urls) = find_entry(&self.json.root, name).and_then(|entry| entry.access_urls.as_ref()) {
+                return access_urls.resolve(&self.name, auth_name);
+            }
+
+            current = self.get_issuer(name);
+        }
+
+        self.json.access_urls.as_ref().map(|access_urls| access_urls.resolve(&self.name, auth_name)).unwrap_or_default()
+    }
+
+
+    /// Policies of a certificate: its own, or those of the closest authority above it having
+    /// policies, when it lets them be inherited
+    fn policies(&self, spec: &Certificate, auth_name: Option<&String>) -> Vec<Policy> {
+        if !spec.policies.is_empty() {
+            return spec.policies.clone();
+        }
+
+        let mut current = auth_name;
+
+        while let Some(name) = current {
+            if let Some(entry) = find_entry(&self.json.root, name).filter(|entry| !entry.policies.is_empty()) {
+                return if entry.inherit_policies { entry.policies.clone() } else { vec![] };
+            }
+
+            current = self.get_issuer(name);
+        }
+
+        vec![]
+    }
+
+
+    /// Subject Key Identifier of an issuer, or the one computed from its key when missing
+    fn key_id(&self, issuer: &X509) -> Result<Vec<u8>, PKIError> {
+        let key = issuer.public_key()?;
+
+        match issuer.subject_key_id() {
+            Some(key_id) => Ok(key_id.as_slice().to_vec()),
+            None => Ok(self.json.key_identifier.unwrap_or_default().compute(&key)?)
+        }
+    }
+
+
+    /// Refuse a certificate breaking the path length or name constraints of the authorities above it.
+    fn check_constraints(&self, cert: &X509, auth_name: &String) -> Result<(), PKIError> {
+        let cname = x509_to_certificate(cert)?.cname;
+        // Authorities between the certificate and the one checked, the certificate itself when a CA
+        let mut intermediates = u32::from(is_ca(cert));
+        let mut current = Some(auth_name);
+
+        while let Some(name) = current {
+            let (issuer, _) = self.find_authority(name)?;
+
+            if let Some(path_len) = issuer.pathlen().filter(|path_len| intermediates > *path_len) {
+                return Err(PKIError::InvalidSpec(format!(
+                    "{} would be authority {} below {}, whose path length is {}", cname, intermediates, name, path_len
+                )));
+            }
+
+            if let Some(name_constraints) = find_entry(&self.json.root, name).and_then(|entry| entry.name_constraints.as_ref()) {
+                name_constraints.check(cert)
+                    .map_err(|err| PKIError::InvalidSpec(format!("{} breaks the name constraints of {}: {}", cname, name, err)))?;
+            }
+
+            intermediates += 1;
+            current = self.get_issuer(name);
+        }
+
+        Ok(())
+    }
+
+
+    /// Validity period of a new certificate, which must end before its issuer's unless
+    /// `outlive_issuer` is set. The configuration default is capped to the issuer's instead.
+    fn validity(&self, spec: &Certificate, issuer: Option<&X509>) -> Result<(Asn1Time, Asn1Time), PKIError> {
+        let parse = |field: &str, value: &String| parse_time(value)
+            .ok_or_else(|| PKIError::InvalidSpec(format!("{} {}: invalid time {:?}", spec.cname, field, value)));
+        let days = spec.validity.unwrap_or(self.configuration.x509_certs_entries.validity);
+
+        let not_before = match &spec.not_before {
+            Some(value) => parse("not_before", value)?,
+            None => Asn1Time::days_from_now(0)?
+        };
+
+        let mut not_after = match (&spec.not_after, &spec.not_before) {
+            (Some(value), _) => parse("not_after", value)?,
+            (None, Some(_)) => Asn1Time::from_unix(unix_time(&not_before)? + i64::from(days) * 86400)?,
+            (None, None) => Asn1Time::days_from_now(days)?
+        };
+
+        if let Some(issuer) = issuer {
+            let explicit = spec.validity.is_some() || spec.not_after.is_some();
+
+            if !explicit && !spec.outlive_issuer && not_after > issuer.not_after() {
+                not_after = Asn1Time::from_unix(unix_time(issuer.not_after())?)?;
+            }
+
+            if !spec.outlive_issuer && not_after > issuer.not_after() {
+                return Err(PKIError::InvalidSpec(format!(
+                    "{} would expire on {}, after its issuer on {}",
+                    spec.cname, not_after.as_ref(), issuer.not_after()
+                )));
+            }
+        }
+
+        if not_after <= not_before {
+            return Err(PKIError::InvalidSpec(format!("{} validity ends before it starts", spec.cname)));
+        }
+
+        Ok((not_before, not_after))
+    }
+
+
+    /// Replace the certificate of an entry, keeping its key unless `new_key` is set, and
+    /// record the previous one in the metadata history. Returns the previous certificate.
+    fn reissue(&mut self, name: &String, validity: Option<u32>, new_key: Option<Key>) -> Result<X509, PKIError> {
+        let entry = find_entry(&self.json.root, name)
+            .filter(|_| !self.pending.contains_key(name))
+            .ok_or_else(|| PKIError::NotFound(format!("{} not found on {}", name, self.name)))?;
+
+        if entry.external_issuer {
+            return Err(PKIError::InvalidSpec(
+                format!("{} is signed by an external issuer, import a new certificate instead", name)
+            ));
+        }
+
+        let spec = Certificate {
+            validity: validity.or(entry.validity),
+            not_before: None,
+            not_after: None,
+            subcerts: vec![],
+            ..entry.clone()
+        };
+        let rekeyed = new_key.is_some();
+        let auth_name = self.get_issuer(name).cloned();
+        let previous = self.get_cert(name)?.clone();
+        let key = match new_key {
+            Some(key) => Some(key),
+            None => self.get_entry(name).ok().map(|(_, key)| key.clone())
+        };
+        let public_key = match &key {
+            Some(key) => key.to_public_pkey()?,
+            None => previous.public_key()?
+        };
+        let args = self.cert_args(&spec, auth_name.as_ref(), public_key, key.as_ref())?;
+
+        let cert = match self.authorities.contains_key(name) {
+            true => create_x509_node(args)?,
+            false => create_x509_leaf(args)?
+        };
+        self.record_serial(auth_name.as_ref().unwrap_or(name), &cert)?;
+        let described = x509_to_certificate(&cert)?;
+
+        if let Some(entry) = find_entry_mut(&mut self.json.root, name) {
+            entry.validity = spec.validity;
+            entry.not_before = None;
+            entry.not_after = None;
+            entry.keylen = described.keylen;
+            entry.curve = described.curve;
+            entry.history.push(PreviousCertificate {
+                serial: previous.serial_number().to_bn()?.to_hex_str()?.to_string(),
+                not_after: previous.not_after().to_string(),
+                rekeyed
+            });
+        }
+
+        match key {
+            None => { self.external.insert(name.to_owned(), cert); },
+            Some(key) if self.authorities.contains_key(name) => { self.authorities.insert(name.to_owned(), (cert, key)); },
+            Some(key) => { self.certs.insert(name.to_owned(), (cert, key)); }
+        }
+
+        self.modified.insert(name.to_owned());
+
+        Ok(previous)
+    }
+
+
+    /// Certificate of the `subject` root key signed by `key`. Both roots sharing their name,
+    /// it is built as a self-signed root and ends with the previous root.
+    fn cross_certificate(&mut self, spec: &Certificate, subject: &X509, signer: (&X509, &Key), old_root: &X509) -> Result<X509, PKIError> {
+        let (signer, key) = signer;
+        let mut args = self.cert_args(spec, None, subject.public_key()?, Some(key))?;
+
+        // Same identifiers as the roots, whichever method made them, for chains to go through
+        args.subject_key_id = self.key_id(subject)?;
+        args.authority_key_id = Some(self.key_id(signer)?);
+
+        if args.not_after > old_root.not_after() {
+            args.not_after = Asn1Time::from_unix(unix_time(old_root.not_after())?)?;
+        }
+
+        let cert = create_x509_node(args)?;
+        self.record_serial(&spec.cname, &cert)?;
+
+        Ok(cert)
+    }
+
+
+    /// Sign again the retired certificates of an authority whose issuer was re-keyed,
+    /// for the certificates it issued before its own re-key to keep chaining up.
+    fn reissue_retired(&mut self, name: &String) -> Result<(), PKIError> {
+        let Some(retired) = self.retired.remove(name) else {
+            return Ok(());
+        };
+
+        let spec = find_entry(&self.json.root, name)
+            .map(|entry| Certificate { not_before: None, not_after: None, subcerts: vec![], ..entry.clone() })
+            .ok_or_else(|| PKIError::NotFound(format!("{} not found on {}", name, self.name)))?;
+        let auth_name = self.get_issuer(name).cloned();
+        let mut reissued = vec![];
+
+        for (cert, key) in retired {
+            let args = self.cert_args(&spec, auth_name.as_ref(), cert.public_key()?, None)?;
+            let cert = create_x509_node(args)?;
+
+            self.record_serial(auth_name.as_ref().unwrap_or(name), &cert)?;
+            reissued.push((cert, key));
+        }
+
+        self.retired.insert(name.to_owned(), reissued);
+        self.modified.insert(name.to_owned());
+
+        Ok(())
+    }
+
+
+    /// Record the serial number of a certificate issued by `issuer` in its index
+    fn record_serial(&mut self, issuer: &String, cert: &X509) -> Result<(), PKIError> {
+        let serial = cert.serial_number().to_bn()?;
+
+        match self.serials.entry(issuer.to_owned()).or_default().record(&serial)? {
+            true => Ok(()),
+            false => Err(PKIError::Inconsistent(
+                format!("serial number {} is already used by {} on {}", serial.to_hex_str()?, issuer, self.name)
+            ))
+        }
+    }
+
+
+    /// Certificates of an authority, current first then retired ones.
+    fn authority_certs(&self, auth_name: &String) -> Result<Vec<&X509>, PKIError> {
+        let (current, _) = self.find_authority(auth_name)?;
+
+        let retired = self.retired.get(auth_name).into_iter().flatten().map(|(cert, _)| cert);
+
+        Ok(std::iter::once(current).chain(retired).collect())
+    }
+
+
+    /// Certificate of `auth_name` that signed `cert`, its current one when none did.
+    fn issuer_cert(&self, cert: &X509, auth_name: &String) -> Result<&X509, PKIError> {
+        let candidates = self.authority_certs(auth_name)?;
+
+        for candidate in candidates.iter() {
+            let public_key = candidate.public_key()?;
+
+            if cert.verify(&public_key)? {
+                return Ok(candidate);
+            }
+        }
+
+        Ok(candidates[0])
+    }
+
+
+    /// Generate the request of a root authority to be signed outside of this PKI. Its
+    /// specification, with the sub certificates to issue later on, is kept as metadata.
+    fn add_pending_authority(&mut self, spec: &Certificate, auth_name: Option<&String>, key: Key) -> Result<&Self, PKIError> {
+        if let Some(auth_name) = auth_name {
+            return Err(PKIError::InvalidSpec(
+                format!("{} is issued by {}, it cannot be signed externally", spec.cname, auth_name)
+            ));
+        }
+
+        let req = create_x509_req(self.cert_args(spec, None, key.to_public_pkey()?, Some(&key))?)?;
+        let public_key = key.to_public_pkey()?;
+        let (keylen, curve) = Key::key_type(&public_key);
+
+        self.json.root = Certificate {
+            ca: true,
+            keylen,
+            curve,
+            ..spec.clone()
+        };
+
+        self.pending.insert(spec.cname.to_owned(), (req.to_pem()?, key));
+        self.modified.insert(spec.cname.to_owned());
+
+        Ok(self)
+    }
+
+
+    fn check_available(&self, name: &String) -> Result<(), PKIError> {
+        if self.pending.contains_key(name) {
+            return Err(PKIError::AlreadyExists(format!("{} already exists on {}", name, self.name)));
+        }
+
+        match self.get_cert(name) {
+            Ok(_) => Err(PKIError::AlreadyExists(format!("{} already exists on {}", name, self.name))),
+            Err(_) => Ok(())
+        }
+    }
+
+
+    fn load_entry(
+        &mut self,
+        entry: &Certificate,
+        auth_name: Option<&String>,
+        entries: &mut HashMap<String, (X509, Option<Key>)>
+    ) -> Result<(), PKIError> {
+        let (cert, key) = entries.remove(&entry.cname)
+            .ok_or_else(|| PKIError::NotFound(format!("{} files not found on {}", entry.cname, self.name)))?;
+
+        if entry.external_key {
+            let issuers = auth_name
+                .ok_or_else(|| PKIError::Inconsistent(format!("{} root key is missing", self.name)))
+                .and_then(|name| self.authority_certs(name))?;
+
+            self.check_entry(entry, &cert, None, &issuers)?;
+            self.external.insert(entry.cname.to_owned(), cert);
+
+            return Ok(());
+        }
+
+        let key = key.ok_or_else(|| PKIError::NotFound(format!("{} key not found on {}", entry.cname, self.name)))?;
+        let issuers = match auth_name {
+            Some(name) => self.authority_certs(name)?,
+            // Only the key of an externally signed root can be checked
+            None if entry.external_issuer => vec![],
+            None => vec![&cert]
+        };
+
+        self.check_entry(entry, &cert, Some(&key), &issuers)?;
+
+        if auth_name.is_some() && !entry.ca && entry.subcerts.is_empty() {
+            self.certs.insert(entry.cname.to_owned(), (cert, key));
+
+            return Ok(());
+        }
+
+        self.authorities.insert(entry.cname.to_owned(), (cert, key));
+
+        for subcert in entry.subcerts.iter() {
+            self.load_entry(subcert, Some(&entry.cname), entries)?;
+        }
+
+        Ok(())
+    }
+
+
+    fn check_entry(
+        &self,
+        entry: &Certificate,
+        cert: &X509,
+        key: Option<&Key>,
+        issuers: &[&X509]
+    ) -> Result<(), PKIError> {
+        let inconsistent = |reason: &str| PKIError::Inconsistent(
+            format!("{} on {}: {}", entry.cname, self.name, reason)
+        );
+        let described = x509_to_certificate(cert)?;
+
+        if described.cname != entry.cname {
+            return Err(inconsistent("certificate common name does not match metadata"));
+        }
+
+        if let Some(key) = key {
+            let pkey = key.to_private_pkey()?;
+
+            if !cert.public_key()?.public_eq(&pkey) {
+                return Err(inconsistent("private key does not match certificate"));
+            }
+        }
+
+        if described.keylen != entry.keylen || described.curve != entry.curve {
+            return Err(inconsistent("key type does not match metadata"));
+        }
+
+        if issuers.is_empty() {
+            return Ok(());
+        }
+
+        let mut named = false;
+
+        // The current authority certificate, or a retired one for entries issued before a re-key
+        for issuer in issuers {
+            if cert.issuer_name().try_cmp(issuer.subject_name())? != Ordering::Equal {
+                continue;
+            }
+
+            named = true;
+            let public_key = issuer.public_key()?;
+
+            if cert.verify(&public_key)? {
+                return Ok(());
+            }
+        }
+
+        if !named {
+            return Err(inconsistent("issuer name does not match its authority"));
+        }
+
+        Err(inconsistent("certificate is not signed by its authority"))
+    }
 }
 
-fn serialize(root: &mut Certificate, cert: &Certificate, cname: &String, auth_cname: &String, key: &Key) -> bool {
+/// Metadata of a new certificate, keeping the settings of its specification
+fn metadata_entry(spec: &Certificate, cert: &X509) -> Result<Certificate, PKIError> {
+    Ok(Certificate {
+        subject: spec.subject.clone(),
+        validity: spec.validity,
+        not_before: spec.not_before.clone(),
+        not_after: spec.not_after.clone(),
+        outlive_issuer: spec.outlive_issuer,
+        external_key: spec.external_key,
+        external_issuer: spec.external_issuer,
+        path_len: spec.path_len,
+        name_constraints: spec.name_constraints.clone(),
+        access_urls: spec.access_urls.clone(),
+        policies: spec.policies.clone(),
+        inherit_policies: spec.inherit_policies,
+        policy_constraints: spec.policy_constraints,
+        inhibit_any_policy: spec.inhibit_any_policy,
+        serials: spec.serials,
+        extensions: spec.extensions.clone(),
+        ..x509_to_certificate(cert)?
+    })
+}
+
+fn unix_time(time: &Asn1TimeRef) -> Result<i64, ErrorStack> {
+    let diff = Asn1Time::from_unix(0)?.diff(time)?;
+
+    Ok(i64::from(diff.days) * 86400 + i64::from(diff.secs))
+}
+
+fn serialize(root: &mut Certificate, cert: &Certificate, auth_cname: &String) -> bool {
     if root.cname == *auth_cname {
         root.subcerts.push(cert.clone());
         return true;
     }
 
     for subcert in root.subcerts.iter_mut() {
-        if serialize(subcert, cert, cname, auth_cname, key) {
+        if serialize(subcert, cert, auth_cname) {
             return true;
         }
     }
 
     false
-}
\ No newline at end of file
+}
+
+fn find_entry<'a>(root: &'a Certificate, cname: &String) -> Option<&'a Certificate> {
+    if root.cname == *cname {
+        return Some(root);
+    }
+
+    root.subcerts.iter().find_map(|subcert| find_entry(subcert, cname))
+}
+
+fn find_entry_mut<'a>(root: &'a mut Certificate, cname: &String) -> Option<&'a mut Certificate> {
+    if root.cname == *cname {
+        return Some(root);
+    }
+
+    root.subcerts.iter_mut().find_map(|subcert| find_entry_mut(subcert, cname))
+}
+
+fn collect_names<'a>(root: &'a Certificate, names: &mut Vec<&'a String>) {
+    names.push(&root.cname);
+
+    for subcert in root.subcerts.iter() {
+        collect_names(subcert, names);
+    }
+}
+
+fn find_issuer<'a>(root: &'a Certificate, cname: &String) -> Option<&'a String> {
+    if root.subcerts.iter().any(|subcert| subcert.cname == *cname) {
+        return Some(&root.cname);
+    }
+
+    root.subcerts.iter().find_map(|subcert| find_issuer(subcert, cname))
+}
@@ -0,0 +1,193 @@
+//! Descriptions of saved PKI entries, printed by the `list`, `show` and `verify` commands.
+use core::fmt;
+
+use openssl::{hash::MessageDigest, x509::{X509NameRef, X509}};
+use serde::Serialize;
+
+use crate::{
+    certificates::{crl::RevocationReason, x509::x509_to_certificate, AltNames, PreviousCertificate, Profile},
+    error::PKIError
+};
+
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryKind {
+    Root,
+    Authority,
+    Certificate,
+    /// Signed from a CSR, without private key
+    External,
+    /// Root authority waiting for its externally signed certificate
+    Pending,
+}
+
+
+/// Summary of a PKI entry. Certificate fields are empty for pending authorities.
+#[derive(Serialize, Clone, Debug)]
+pub struct EntryInfo {
+    pub cname: String,
+    pub kind: EntryKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Hexadecimal serial number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keylen: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curve: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub san: Option<AltNames>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    /// Hexadecimal SHA-256 fingerprint of the DER certificate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked: Option<RevocationReason>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PreviousCertificate>,
+}
+
+
+/// Certificate of a PKI that failed verification
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct VerifyFailure {
+    pub cname: String,
+    pub reason: String,
+}
+
+
+impl EntryInfo {
+    pub fn pending(cname: &str) -> EntryInfo {
+        EntryInfo {
+            cname: cname.to_string(),
+            kind: EntryKind::Pending,
+            issuer: None,
+            subject: None,
+            serial: None,
+            not_before: None,
+            not_after: None,
+            keylen: None,
+            curve: None,
+            san: None,
+            profile: None,
+            fingerprint: None,
+            revoked: None,
+            history: vec![]
+        }
+    }
+
+
+    pub fn from_x509(cert: &X509, kind: EntryKind, issuer: Option<&String>) -> Result<EntryInfo, PKIError> {
+        let described = x509_to_certificate(cert)?;
+
+        Ok(EntryInfo {
+            kind,
+            issuer: issuer.cloned(),
+            subject: Some(name_to_string(cert.subject_name())),
+            serial: Some(cert.serial_number().to_bn()?.to_hex_str()?.to_string()),
+            not_before: Some(cert.not_before().to_string()),
+            not_after: Some(cert.not_after().to_string()),
+            keylen: described.keylen,
+            curve: described.curve,
+            san: described.san.filter(|san| *san != AltNames::default()),
+            fingerprint: Some(hex(&cert.digest(MessageDigest::sha256())?)),
+            ..EntryInfo::pending(&described.cname)
+        })
+    }
+}
+
+
+impl fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryKind::Root => f.pad("root"),
+            EntryKind::Authority => f.pad("authority"),
+            EntryKind::Certificate => f.pad("certificate"),
+            EntryKind::External => f.pad("external"),
+            EntryKind::Pending => f.pad("pending"),
+        }
+    }
+}
+
+
+impl fmt::Display for EntryInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = match (self.keylen, &self.curve) {
+            (Some(keylen), _) => Some(format!("RSA {}", keylen)),
+            (_, Some(curve)) => Some(curve.to_owned()),
+            _ => None
+        };
+        let san = self.san.as_ref().map(|san| {
+            let names = [("DNS", &san.dns), ("IP", &san.ip), ("email", &san.email), ("URI", &san.uri)];
+
+            names.iter()
+                .flat_map(|(kind, values)| values.iter().map(move |value| format!("{}:{}", kind, value)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        });
+
+        let fields = [
+            ("Common name", Some(self.cname.to_owned())),
+            ("Kind", Some(self.kind.to_string())),
+            ("Issuer", self.issuer.clone()),
+            ("Subject", self.subject.clone()),
+            ("Serial", self.serial.clone()),
+            ("Not before", self.not_before.clone()),
+            ("Not after", self.not_after.clone()),
+            ("Key", key),
+            ("Alt names", san),
+            ("Profile", self.profile.map(|profile| profile.to_string())),
+            ("SHA-256", self.fingerprint.clone()),
+            ("Revoked", self.revoked.map(|reason| reason.to_string())),
+        ];
+
+        for (field, value) in fields {
+            if let Some(value) = value {
+                writeln!(f, "{:<12} {}", format!("{}:", field), value)?;
+            }
+        }
+
+        for previous in self.history.iter() {
+            let rekeyed = if previous.rekeyed { ", re-keyed" } else { "" };
+
+            writeln!(f, "{:<12} {} (until {}{})", "Replaced:", previous.serial, previous.not_after, rekeyed)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+impl fmt::Display for VerifyFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.cname, self.reason)
+    }
+}
+
+
+/// One line `C=.., O=.., CN=..` form of a name
+pub fn name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let field = entry.object().nid().short_name().unwrap_or("?");
+            let value = entry.data().as_utf8().map(|value| value.to_string()).unwrap_or_default();
+
+            format!("{}={}", field, value)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<String>>().join(":")
+}
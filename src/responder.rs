@@ -0,0 +1,236 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration
+};
+
+use log::{debug, error, info, warn};
+use openssl::base64;
+
+use crate::{
+    certificates::{
+        chain::is_ocsp_signer,
+        ocsp::{create_response, error_response, parse_request, OcspArgs, ResponseStatus}
+    },
+    error::{ManagerError, PKIError},
+    pki::{OcspIssuer, Pki}
+};
+
+
+/// Largest request accepted, headers and body, OCSP requests are a few hundred bytes
+const MAX_REQUEST_LEN: u64 = 64 * 1024;
+/// Time given to a client to send its request, or to read the response
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// RFC 6960 OCSP responder over HTTP, answering for the authorities of a PKI
+pub struct OcspResponder<'a> {
+    pki: &'a Pki,
+    /// Delegated OCSP signing certificates, used instead of their issuer key
+    responders: Vec<String>,
+    next_update: u32,
+}
+
+
+impl<'a> OcspResponder<'a> {
+    pub fn new(pki: &'a Pki, responders: Vec<String>, next_update: u32) -> Result<OcspResponder<'a>, PKIError> {
+        // Clients only accept responses signed by their issuer, or by a certificate it
+        // delegated OCSP signing to
+        for name in responders.iter() {
+            let (cert, _) = pki.get_entry(name)?;
+            let issuer = pki.get_issuer(name)
+                .ok_or_else(|| PKIError::InvalidSpec(format!("{} is not issued by an authority of {}", name, pki.name)))?;
+
+            if !is_ocsp_signer(cert) {
+                return Err(PKIError::InvalidSpec(
+                    format!("{} cannot sign OCSP responses, issue it with the ocsp-signing profile", name)
+                ));
+            }
+
+            let issuer_key = pki.get_cert(issuer)?.public_key()?;
+
+            if !cert.verify(&issuer_key)? {
+                return Err(PKIError::InvalidSpec(
+                    format!("{} is not signed by the current certificate of {}", name, issuer)
+                ));
+            }
+        }
+
+        Ok(OcspResponder { pki, responders, next_update })
+    }
+
+
+    pub fn serve(&self, listen: &str) -> Result<(), ManagerError> {
+        let listener = TcpListener::bind(listen)?;
+
+        info!("OCSP responder for {} listening on {}", self.pki.name, listen);
+
+        for stream in listener.incoming() {
+            match stream.map_err(ManagerError::from).and_then(|stream| self.handle(stream)) {
+                Ok(_) => (),
+                Err(err) => warn!("Cannot answer OCSP request: {}", err)
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// DER encoded response to a DER encoded OCSP request
+    pub fn respond(&self, request: &[u8]) -> Vec<u8> {
+        let request = match parse_request(request) {
+            Some(request) if !request.ids.is_empty() => request,
+            _ => return error_response(ResponseStatus::MalformedRequest)
+        };
+
+        let mut issuer: Option<OcspIssuer> = None;
+        let mut responses = vec![];
+
+        for id in request.ids.iter() {
+            match self.pki.ocsp_status(id) {
+                Ok(Some((found, status))) if issuer.as_ref().is_none_or(|issuer| issuer.same(&found)) => {
+                    issuer = Some(found);
+                    responses.push((id, status));
+                },
+                // A single response must be signed by one authority key
+                Ok(_) => return error_response(ResponseStatus::Unauthorized),
+                Err(err) => {
+                    error!("Cannot find OCSP status: {}", err);
+                    return error_response(ResponseStatus::InternalError);
+                }
+            }
+        }
+
+        let Some(issuer) = issuer else {
+            return error_response(ResponseStatus::Unauthorized);
+        };
+
+        // Delegated responders are certified by the current authority key only
+        let delegated = self.responders.iter()
+            .filter(|_| !issuer.retired)
+            .find(|name| self.pki.get_issuer(name) == Some(issuer.name));
+
+        debug!("Answering for {} with {}", issuer.name, delegated.unwrap_or(issuer.name));
+
+        let signer = match delegated {
+            Some(name) => self.pki.get_entry(name).map(|(cert, key)| (cert, key)),
+            None => Ok((issuer.cert, issuer.key))
+        };
+
+        let response = signer
+            .map_err(ManagerError::from)
+            .and_then(|(responder, responder_key)| Ok(create_response(OcspArgs {
+                responder,
+                responder_key,
+                delegated: delegated.is_some(),
+                next_update: self.next_update,
+                nonce: request.nonce,
+                responses,
+                signature: self.pki.signature(responder_key)
+            })?));
+
+        response.unwrap_or_else(|err| {
+            error!("Cannot sign OCSP response: {}", err);
+            error_response(ResponseStatus::InternalError)
+        })
+    }
+
+
+    // Private
+    fn handle(&self, mut stream: TcpStream) -> Result<(), ManagerError> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut reader = BufReader::new((&stream).take(MAX_REQUEST_LEN));
+        let mut request_line = String::new();
+        let mut content_length = 0;
+        let mut complete = true;
+
+        reader.read_line(&mut request_line)?;
+
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+
+            // Cut by the limit or the client before the end of the headers
+            if !header.ends_with('\n') {
+                complete = false;
+                break;
+            }
+
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or_default();
+                }
+            }
+        }
+
+        // Left of the limit for the body
+        let available = reader.buffer().len() as u64 + reader.get_ref().limit();
+
+        let request = match request_line.split_whitespace().collect::<Vec<&str>>()[..] {
+            _ if !complete && available == 0 => Err("413 Payload Too Large"),
+            _ if !complete || !request_line.ends_with('\n') => Err("400 Bad Request"),
+            ["GET", path, ..] => {
+                // The request is the last segment, after the path of the responder URL
+                let encoded = percent_decode(path.rsplit('/').next().unwrap_or_default());
+
+                base64::decode_block(&encoded).map_err(|_| "400 Bad Request")
+            },
+            ["POST", ..] if content_length as u64 > available => Err("413 Payload Too Large"),
+            ["POST", ..] => {
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body)?;
+
+                Ok(body)
+            },
+            _ => Err("400 Bad Request")
+        };
+
+        let (status, body) = match request {
+            Ok(request) => ("200 OK", self.respond(&request)),
+            Err(status) => (status, error_response(ResponseStatus::MalformedRequest))
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/ocsp-response\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            body.len()
+        )?;
+        stream.write_all(&body)?;
+
+        Ok(())
+    }
+}
+
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,59 @@
+//! Fixtures shared by the integration tests, each one using part of them
+#![allow(dead_code)]
+
+use openssl::{asn1::Asn1Time, pkey::{PKey, Public}};
+use pkimgr::{
+    certificates::{access::AccessUrls, serial, signature::Signature, CertArgs, Certificate, KeyIdentifier, Profile, Subject},
+    key::Key,
+    pki::Pki,
+    Configuration, DEFAULT_CONFIGURATION
+};
+
+pub fn configuration() -> Configuration {
+    serde_json::from_str(DEFAULT_CONFIGURATION).unwrap()
+}
+
+/// Empty PKI with the default configuration
+pub fn new_pki(name: &str) -> Pki {
+    Pki::new(&name.to_string(), configuration())
+}
+
+pub fn key() -> Key {
+    Key::new(None, Some("secp384r1".to_string())).unwrap()
+}
+
+pub fn spec(cname: &str) -> Certificate {
+    Certificate { cname: cname.to_string(), ..Certificate::default() }
+}
+
+/// Text dump of a certificate of the PKI
+pub fn text(pki: &Pki, name: &str) -> String {
+    String::from_utf8(pki.get_cert(&name.to_string()).unwrap().to_text().unwrap()).unwrap()
+}
+
+/// Arguments of a certificate valid `days` from now, signed with `authority_pkey`, with a random
+/// serial number and nothing else set. Callers override the fields they need.
+pub fn cert_args(name: &str, public_key: PKey<Public>, authority_pkey: Key, days: u32) -> CertArgs {
+    CertArgs {
+        authority_issuer: None,
+        signature: Signature::for_key(&authority_pkey),
+        authority_pkey,
+        subject_key_id: KeyIdentifier::default().compute(&public_key).unwrap(),
+        public_key,
+        name: name.to_string(),
+        subject: Subject::default(),
+        not_before: Asn1Time::days_from_now(0).unwrap(),
+        not_after: Asn1Time::days_from_now(days).unwrap(),
+        authority_key_id: None,
+        san: None,
+        profile: Profile::default(),
+        serial: serial::random().unwrap(),
+        path_len: None,
+        name_constraints: None,
+        access_urls: AccessUrls::default(),
+        policies: vec![],
+        policy_constraints: None,
+        inhibit_any_policy: None,
+        extensions: vec![]
+    }
+}
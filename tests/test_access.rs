@@ -0,0 +1,82 @@
+mod common;
+
+mod access_tests {
+    use openssl::{nid::Nid, x509::X509};
+    use pkimgr::certificates::{access::AccessUrls, Certificate};
+
+    use crate::common::{key, new_pki, spec};
+
+    fn urls(crl: &str, ocsp: &str, ca_issuers: &str) -> AccessUrls {
+        AccessUrls { crl: vec![crl.to_string()], ocsp: vec![ocsp.to_string()], ca_issuers: vec![ca_issuers.to_string()] }
+    }
+
+    fn crl_urls(cert: &X509) -> Vec<String> {
+        cert.crl_distribution_points().iter()
+            .flat_map(|points| points.iter())
+            .filter_map(|point| point.distpoint()?.fullname())
+            .flat_map(|names| names.iter().filter_map(|name| name.uri().map(str::to_string)).collect::<Vec<String>>())
+            .collect()
+    }
+
+    fn access_urls(cert: &X509, method: Nid) -> Vec<String> {
+        cert.authority_info().iter()
+            .flat_map(|access| access.iter())
+            .filter(|access| access.method().nid() == method)
+            .filter_map(|access| access.location().uri().map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn test_access_urls() {
+        let mut pki = new_pki("access");
+        let root = "Root".to_string();
+        let partner = "Partner CA".to_string();
+
+        pki.json.access_urls = Some(urls(
+            "http://pki.example.com/{pki}/{issuer}.crl",
+            "http://ocsp.example.com/{pki}",
+            "http://pki.example.com/{pki}/{issuer}.crt"
+        ));
+
+        let partner_urls = urls(
+            "http://partner.example.com/{issuer}.crl",
+            "http://ocsp.partner.example.com",
+            "http://partner.example.com/ca.crt"
+        );
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&root), key()).unwrap();
+        pki.add_authority(&Certificate { access_urls: Some(partner_urls), ..spec("Partner CA") }, Some(&root), key()).unwrap();
+        pki.add_certificate(&spec("web"), &"Intermediate".to_string(), key()).unwrap();
+        pki.add_certificate(&spec("api"), &partner, key()).unwrap();
+
+        let cert = |name: &str| pki.get_cert(&name.to_string()).unwrap().clone();
+
+        assert!(crl_urls(&cert("Root")).is_empty());
+        assert!(cert("Root").authority_info().is_none());
+        assert_eq!(crl_urls(&cert("Intermediate")), vec!["http://pki.example.com/access/Root.crl"]);
+        assert_eq!(crl_urls(&cert("web")), vec!["http://pki.example.com/access/Intermediate.crl"]);
+        assert_eq!(access_urls(&cert("web"), Nid::AD_OCSP), vec!["http://ocsp.example.com/access"]);
+        assert_eq!(access_urls(&cert("web"), Nid::AD_CA_ISSUERS), vec!["http://pki.example.com/access/Intermediate.crt"]);
+        // The authority URLs apply below it, the PKI ones to the authority itself
+        assert_eq!(crl_urls(&cert("Partner CA")), vec!["http://pki.example.com/access/Root.crl"]);
+        assert_eq!(crl_urls(&cert("api")), vec!["http://partner.example.com/Partner%20CA.crl"]);
+        assert_eq!(access_urls(&cert("api"), Nid::AD_OCSP), vec!["http://ocsp.partner.example.com"]);
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_access_urls_validation() {
+        assert!(urls("http://pki/{pki}.crl", "http://ocsp", "http://pki/{issuer}.crt").validate().is_ok());
+        assert!(urls("http://pki/{serial}.crl", "http://ocsp", "http://pki").validate().is_err());
+        assert!(urls("pki.crl", "http://ocsp", "http://pki").validate().is_err());
+        assert!(urls("http://pki/a b.crl", "http://ocsp", "http://pki").validate().is_err());
+
+        let leaf_with_urls = Certificate {
+            subcerts: vec![Certificate { access_urls: Some(AccessUrls::default()), ..spec("web") }],
+            ..spec("Root")
+        };
+
+        assert!(leaf_with_urls.validate().is_err());
+    }
+}
@@ -0,0 +1,141 @@
+mod common;
+
+mod constraints_tests {
+    use std::{env, fs};
+
+    use pkimgr::{
+        certificates::{
+            constraints::{NameConstraints, Subtrees},
+            AltNames, Certificate, Subject
+        },
+        cli::Pkimgr,
+        pki::Pki
+    };
+
+    use crate::common::{configuration, key, new_pki, spec};
+
+    fn leaf(cname: &str, dns: &[&str], ip: &[&str]) -> Certificate {
+        let san = AltNames {
+            dns: dns.iter().map(|dns| dns.to_string()).collect(),
+            ip: ip.iter().map(|ip| ip.to_string()).collect(),
+            ..AltNames::default()
+        };
+
+        Certificate { san: Some(san), ..spec(cname) }
+    }
+
+    fn create_pki(intermediate: Certificate) -> Pki {
+        let mut pki = new_pki("constraints");
+
+        pki.add_authority(&Certificate { path_len: Some(1), ..spec("Root") }, None, key()).unwrap();
+        pki.add_authority(&intermediate, Some(&"Root".to_string()), key()).unwrap();
+
+        pki
+    }
+
+    #[test]
+    fn test_path_length() {
+        let mut pki = create_pki(Certificate { path_len: Some(0), ..spec("Intermediate") });
+        let root = "Root".to_string();
+        let intermediate = "Intermediate".to_string();
+
+        assert_eq!(pki.get_cert(&intermediate).unwrap().pathlen(), Some(0));
+        assert!(pki.add_certificate(&spec("web"), &intermediate, key()).is_ok());
+        assert!(pki.add_authority(&spec("Sub"), Some(&intermediate), key()).is_err());
+        // A second authority right below the root is within its path length
+        assert!(pki.add_authority(&spec("Other"), Some(&root), key()).is_ok());
+        assert!(pki.add_authority(&spec("Sub"), Some(&"Other".to_string()), key()).is_err());
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_name_constraints() {
+        let name_constraints = NameConstraints {
+            permitted: Subtrees {
+                dns: vec!["example.com".to_string()],
+                ip: vec!["10.0.0.0/8".to_string()],
+                dir_name: vec!["C=UK, ST=UK, O=Partner".to_string()],
+                ..Subtrees::default()
+            },
+            excluded: Subtrees {
+                dns: vec!["secret.example.com".to_string()],
+                ..Subtrees::default()
+            }
+        };
+        let mut pki = create_pki(Certificate { name_constraints: Some(name_constraints), ..spec("Intermediate") });
+        let intermediate = "Intermediate".to_string();
+        let partner = |cert: Certificate| Certificate {
+            subject: Some(Subject { organization: Some("Partner".to_string()), ..Subject::default() }),
+            ..cert
+        };
+
+        assert!(pki.add_certificate(&partner(leaf("api", &["api.example.com"], &["10.1.2.3"])), &intermediate, key()).is_ok());
+        assert!(pki.add_certificate(&partner(leaf("other", &["other.org"], &[])), &intermediate, key()).is_err());
+        assert!(pki.add_certificate(&partner(leaf("secret", &["db.secret.example.com"], &[])), &intermediate, key()).is_err());
+        assert!(pki.add_certificate(&partner(leaf("lan", &["lan.example.com"], &["192.168.1.1"])), &intermediate, key()).is_err());
+        assert!(pki.add_certificate(&leaf("home", &["home.example.com"], &[]), &intermediate, key()).is_err());
+        // The common name stands for a DNS name without alternative names
+        assert!(pki.add_certificate(&partner(spec("www.other.org")), &intermediate, key()).is_err());
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_constraints_validation() {
+        let invalid = |subtrees: Subtrees| Certificate {
+            subcerts: vec![spec("web")],
+            name_constraints: Some(NameConstraints { permitted: subtrees, ..NameConstraints::default() }),
+            ..spec("Root")
+        }.validate();
+
+        assert!(invalid(Subtrees { ip: vec!["10.0.0.0".to_string()], ..Subtrees::default() }).is_err());
+        assert!(invalid(Subtrees { ip: vec!["10.0.0.0/33".to_string()], ..Subtrees::default() }).is_err());
+        assert!(invalid(Subtrees { dns: vec!["bad..name".to_string()], ..Subtrees::default() }).is_err());
+        assert!(invalid(Subtrees { dir_name: vec!["O".to_string()], ..Subtrees::default() }).is_err());
+        assert!(invalid(Subtrees { email: vec![".example.com".to_string()], ..Subtrees::default() }).is_ok());
+
+        let leaf_with_path_len = Certificate {
+            subcerts: vec![Certificate { path_len: Some(0), ..spec("web") }],
+            ..spec("Root")
+        };
+
+        assert!(leaf_with_path_len.validate().is_err());
+    }
+
+    #[test]
+    fn test_create_fails_on_constraints() {
+        let base_path = env::temp_dir().join(format!("pkimgr-constraints-{}", std::process::id()));
+        let configuration = configuration();
+        let spec_path = base_path.join("spec.json");
+        let spec = r#"
+        {
+            "pki_name": "constraints",
+            "root": {
+                "cname": "Root",
+                "curve": "secp384r1",
+                "subcerts": [
+                    {
+                        "cname": "Inter2",
+                        "curve": "secp384r1",
+                        "name_constraints": { "permitted": { "dns": ["example.com"] } },
+                        "subcerts": [
+                            { "cname": "web", "curve": "secp384r1", "san": { "dns": ["web.other.org"] }, "subcerts": [] }
+                        ]
+                    }
+                ]
+            }
+        }"#;
+
+        fs::create_dir_all(&base_path).unwrap();
+        fs::write(&spec_path, spec).unwrap();
+
+        let err = Pkimgr::new(configuration, base_path.clone())
+            .create_from_file(fs::File::open(&spec_path).unwrap())
+            .err()
+            .unwrap();
+
+        assert!(err.to_string().contains("breaks the name constraints of Inter2"), "{}", err);
+        assert!(!base_path.join("constraints").exists());
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+mod csr_tests {
+    use openssl::{
+        hash::MessageDigest,
+        nid::Nid,
+        stack::Stack,
+        x509::{extension::SubjectAlternativeName, X509NameBuilder, X509Req, X509ReqBuilder}
+    };
+    use pkimgr::{
+        certificates::{csr::{read_csr, requested_names, CsrPolicy}, AltNames, Certificate},
+        key::Key,
+        pki::Pki,
+        Configuration, DEFAULT_CONFIGURATION
+    };
+
+    fn create_pki() -> Pki {
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let mut pki = Pki::new(&"csr".to_string(), configuration);
+        let key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+
+        pki.add_authority(&Certificate { cname: "Root".to_string(), ..Certificate::default() }, None, key).unwrap();
+
+        pki
+    }
+
+    fn create_csr(key: &Key) -> X509Req {
+        let pkey = key.to_private_pkey().unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("C", "FR").unwrap();
+        name.append_entry_by_text("OU", "nodes").unwrap();
+        name.append_entry_by_text("CN", "node-1").unwrap();
+        let name = name.build();
+
+        let mut builder = X509ReqBuilder::new().unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+
+        let mut extensions = Stack::new().unwrap();
+        extensions.push(
+            SubjectAlternativeName::new()
+                .dns("node-1.internal")
+                .ip("10.0.0.1")
+                .build(&builder.x509v3_context(None))
+                .unwrap()
+        ).unwrap();
+        builder.add_extensions(&extensions).unwrap();
+        builder.sign(&pkey, MessageDigest::sha384()).unwrap();
+
+        builder.build()
+    }
+
+    #[test]
+    fn test_sign_csr() {
+        let mut pki = create_pki();
+        let root = "Root".to_string();
+        let key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let req = read_csr(&create_csr(&key).to_pem().unwrap()).unwrap();
+        let extra = AltNames { dns: vec!["node.internal".to_string()], ip: vec!["10.0.0.1".to_string()], ..AltNames::default() };
+        let spec = Certificate { san: Some(extra), ..Certificate::default() };
+
+        pki.sign_csr(&spec, &root, &req, CsrPolicy::AltNames, &["internal".to_string()]).unwrap();
+
+        let cert = pki.get_cert(&"node-1".to_string()).unwrap();
+        let (root_cert, _) = pki.get_entry(&root).unwrap();
+        let dns: Vec<String> = cert.subject_alt_names().unwrap().iter()
+            .filter_map(|name| name.dnsname().map(str::to_string))
+            .collect();
+
+        assert!(cert.verify(&root_cert.public_key().unwrap()).unwrap());
+        assert!(cert.public_key().unwrap().public_eq(&key.to_private_pkey().unwrap()));
+        assert_eq!(dns, vec!["node-1.internal", "node.internal"]);
+        assert!(pki.get_entry(&"node-1".to_string()).is_err());
+        assert!(cert.subject_name().entries_by_nid(Nid::ORGANIZATIONALUNITNAME).next().is_none());
+
+        // The same name cannot be issued twice
+        assert!(pki.sign_csr(&spec, &root, &req, CsrPolicy::AltNames, &["internal".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_csr_policies() {
+        let mut pki = create_pki();
+        let root = "Root".to_string();
+        let req = create_csr(&Key::new(None, Some("secp384r1".to_string())).unwrap());
+        let san = AltNames { dns: vec!["node-1.internal".to_string()], ip: vec!["10.0.0.1".to_string()], ..AltNames::default() };
+        let spec = |cname: &str| Certificate { cname: cname.to_string(), san: Some(san.clone()), ..Certificate::default() };
+
+        pki.sign_csr(&spec("full"), &root, &req, CsrPolicy::Full, &[]).unwrap();
+        pki.sign_csr(&Certificate { san: None, ..spec("cn") }, &root, &req, CsrPolicy::CommonName, &[]).unwrap();
+
+        let full = pki.get_cert(&"full".to_string()).unwrap();
+        let cn = pki.get_cert(&"cn".to_string()).unwrap();
+        let text = |cert: &openssl::x509::X509, nid: Nid| cert.subject_name().entries_by_nid(nid)
+            .next()
+            .map(|entry| entry.data().as_utf8().unwrap().to_string());
+
+        assert_eq!(text(full, Nid::COUNTRYNAME).as_deref(), Some("FR"));
+        assert_eq!(text(full, Nid::ORGANIZATIONALUNITNAME).as_deref(), Some("nodes"));
+        assert_eq!(text(full, Nid::ORGANIZATIONNAME), None);
+        assert_eq!(text(cn, Nid::ORGANIZATIONALUNITNAME), None);
+        assert!(cn.subject_alt_names().is_none());
+    }
+
+    #[test]
+    fn test_reject_forged_csr() {
+        let mut pki = create_pki();
+        let req = create_csr(&Key::new(None, Some("secp384r1".to_string())).unwrap());
+        let other = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let mut forged = X509ReqBuilder::new().unwrap();
+
+        forged.set_subject_name(req.subject_name()).unwrap();
+        forged.set_pubkey(&other.to_public_pkey().unwrap()).unwrap();
+        forged.sign(&Key::new(None, Some("secp384r1".to_string())).unwrap().to_private_pkey().unwrap(), MessageDigest::sha384()).unwrap();
+
+        assert!(pki.sign_csr(&Certificate::default(), &"Root".to_string(), &forged.build(), CsrPolicy::AltNames, &[]).is_err());
+    }
+
+    #[test]
+    fn test_csr_alt_names_allowed() {
+        let mut pki = create_pki();
+        let root = "Root".to_string();
+        let req = create_csr(&Key::new(None, Some("secp384r1".to_string())).unwrap());
+        let ip = AltNames { ip: vec!["10.0.0.1".to_string()], ..AltNames::default() };
+        let spec = |cname: &str, san: &AltNames| Certificate { cname: cname.to_string(), san: Some(san.clone()), ..Certificate::default() };
+
+        // Names neither configured nor in an allowed domain are refused
+        assert!(pki.sign_csr(&Certificate::default(), &root, &req, CsrPolicy::AltNames, &[]).is_err());
+        assert!(pki.sign_csr(&spec("other", &ip), &root, &req, CsrPolicy::AltNames, &["example.com".to_string()]).is_err());
+        assert!(pki.sign_csr(&spec("ip", &AltNames::default()), &root, &req, CsrPolicy::AltNames, &["internal".to_string()]).is_err());
+
+        pki.sign_csr(&spec("allowed", &ip), &root, &req, CsrPolicy::AltNames, &["Internal".to_string()]).unwrap();
+    }
+
+    #[test]
+    fn test_read_csr() {
+        let key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let pkey = key.to_private_pkey().unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        // An attribute openssl has no short name for
+        name.append_entry_by_text("1.3.6.1.4.1.55555.7", "tier-1").unwrap();
+        name.append_entry_by_text("CN", "node-2").unwrap();
+
+        let mut builder = X509ReqBuilder::new().unwrap();
+        builder.set_subject_name(&name.build()).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        builder.sign(&pkey, MessageDigest::sha384()).unwrap();
+        let req = builder.build();
+
+        // PEM after blank lines, or DER
+        let pem = [b"\n\n".to_vec(), req.to_pem().unwrap()].concat();
+
+        assert!(read_csr(&pem).is_ok());
+        assert!(read_csr(&req.to_der().unwrap()).is_ok());
+
+        let names = requested_names(&read_csr(&pem).unwrap()).unwrap();
+
+        assert_eq!(names.common_name.as_deref(), Some("node-2"));
+        assert_eq!(names.subject.rdns[0]["1.3.6.1.4.1.55555.7"], "tier-1");
+    }
+}
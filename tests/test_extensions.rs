@@ -0,0 +1,72 @@
+mod common;
+
+mod extensions_tests {
+    use pkimgr::certificates::{extensions::CustomExtension, Certificate};
+
+    use crate::common::{key, new_pki, spec, text};
+
+    const PRIVATE_OID: &str = "1.3.6.1.4.1.55555.9";
+
+    fn extension(oid: &str) -> CustomExtension {
+        CustomExtension { oid: oid.to_string(), ..CustomExtension::default() }
+    }
+
+    #[test]
+    fn test_extensions() {
+        let mut pki = new_pki("extensions");
+        let intermediate = Certificate {
+            extensions: vec![CustomExtension { conf: Some("ASN1:UTF8String:Intermediate tier".to_string()), ..extension(PRIVATE_OID) }],
+            ..spec("Intermediate")
+        };
+        let web = Certificate {
+            extensions: vec![
+                // OCSP Must-Staple, a TLS feature list holding status_request
+                CustomExtension { value_der_base64: Some("MAMCAQU=".to_string()), ..extension("1.3.6.1.5.5.7.1.24") },
+                CustomExtension { value_string: Some("Web tier".to_string()), ..extension(PRIVATE_OID) }
+            ],
+            ..spec("web")
+        };
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&intermediate, Some(&"Root".to_string()), key()).unwrap();
+        pki.add_certificate(&web, &"Intermediate".to_string(), key()).unwrap();
+
+        assert!(text(&pki, "Intermediate").contains(&format!("{}: \n", PRIVATE_OID)));
+        assert!(text(&pki, "Intermediate").contains("Intermediate tier"));
+        assert!(text(&pki, "web").contains("TLS Feature: \n                status_request"));
+        assert!(text(&pki, "web").contains("Web tier"));
+        assert!(!text(&pki, "Root").contains(PRIVATE_OID));
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_critical_extension() {
+        let mut pki = new_pki("extensions");
+        // Certificate Transparency poison, a critical NULL
+        let poison = CustomExtension { critical: true, value_der_base64: Some("BQA=".to_string()), ..extension("1.3.6.1.4.1.11129.2.4.3") };
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_certificate(&Certificate { extensions: vec![poison], ..spec("web") }, &"Root".to_string(), key()).unwrap();
+
+        assert!(text(&pki, "web").contains("CT Precertificate Poison: critical"));
+    }
+
+    #[test]
+    fn test_extensions_validation() {
+        let with = |extensions: Vec<CustomExtension>| Certificate { extensions, ..spec("web") }.validate();
+        let string = |oid: &str| CustomExtension { value_string: Some("value".to_string()), ..extension(oid) };
+
+        assert!(with(vec![string(PRIVATE_OID)]).is_ok());
+        assert!(with(vec![string("not.an.oid")]).is_err());
+        assert!(with(vec![extension(PRIVATE_OID)]).is_err());
+        assert!(with(vec![CustomExtension { conf: Some("ASN1:NULL".to_string()), ..string(PRIVATE_OID) }]).is_err());
+        assert!(with(vec![string(PRIVATE_OID), string(PRIVATE_OID)]).is_err());
+        // Set from the specification fields
+        assert!(with(vec![string("2.5.29.19")]).is_err());
+        assert!(with(vec![CustomExtension { value_der_base64: Some("BQA=".to_string()), ..extension("1.3.6.1.5.5.7.48.1.5") }]).is_err());
+        assert!(with(vec![CustomExtension { value_der_base64: Some("not base64!".to_string()), ..extension(PRIVATE_OID) }]).is_err());
+        // NULL followed by a stray byte
+        assert!(with(vec![CustomExtension { value_der_base64: Some("BQAF".to_string()), ..extension(PRIVATE_OID) }]).is_err());
+        assert!(with(vec![CustomExtension { conf: Some("ASN1:NOTATYPE:x".to_string()), ..extension(PRIVATE_OID) }]).is_err());
+    }
+}
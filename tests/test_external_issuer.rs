@@ -0,0 +1,90 @@
+mod external_issuer_tests {
+    use std::{env, fs, io::Write};
+
+    use openssl::x509::{X509Req, X509};
+    use pkimgr::{
+        certificates::{csr::CsrPolicy, Certificate},
+        cli::Pkimgr,
+        key::Key,
+        pki::Pki,
+        Configuration, DEFAULT_CONFIGURATION
+    };
+
+    const SPEC: &str = r#"
+    {
+        "pki_name": "external",
+        "root": {
+            "cname": "Intermediate",
+            "curve": "secp384r1",
+            "external_issuer": true,
+            "subcerts": [
+                { "cname": "leaf", "curve": "secp384r1", "subcerts": [] }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_import_signed_authority() {
+        let base_path = env::temp_dir().join(format!("pkimgr-external-{}", std::process::id()));
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let pki_path = base_path.join("external");
+        let spec_path = base_path.join("spec.json");
+
+        fs::create_dir_all(&base_path).unwrap();
+        fs::File::create(&spec_path).unwrap().write_all(SPEC.as_bytes()).unwrap();
+
+        Pkimgr::new(configuration.clone(), base_path.clone())
+            .create_from_file(fs::File::open(&spec_path).unwrap())
+            .unwrap();
+
+        assert!(!pki_path.join("certs/Intermediate.crt").exists());
+        assert!(!pki_path.join("certs/leaf.crt").exists());
+
+        // The corporate root signing the request is not managed by this PKI
+        let mut corporate = Pki::new(&"corporate".to_string(), configuration.clone());
+        let root = "Corporate Root".to_string();
+        let req = X509Req::from_pem(&fs::read(pki_path.join("csr/Intermediate.csr")).unwrap()).unwrap();
+
+        corporate.add_authority(
+            &Certificate { cname: root.to_owned(), ..Certificate::default() },
+            None,
+            Key::new(None, Some("secp384r1".to_string())).unwrap()
+        ).unwrap();
+        corporate.sign_csr(&Certificate::default(), &root, &req, CsrPolicy::Full, &[]).unwrap();
+
+        let (root_cert, _) = corporate.get_entry(&root).unwrap();
+        let signed_path = base_path.join("signed.crt");
+        let wrong_path = base_path.join("wrong.crt");
+
+        fs::write(&signed_path, corporate.get_cert(&"Intermediate".to_string()).unwrap().to_pem().unwrap()).unwrap();
+        fs::write(&wrong_path, root_cert.to_pem().unwrap()).unwrap();
+
+        let mut manager = Pkimgr::new(configuration.clone(), base_path.clone());
+        let name = "external".to_string();
+
+        assert!(manager.import_signed(&name, &wrong_path).is_err());
+        manager.import_signed(&name, &signed_path).unwrap();
+        assert!(manager.import_signed(&name, &signed_path).is_err());
+
+        let intermediate = X509::from_pem(&fs::read(pki_path.join("certs/Intermediate.crt")).unwrap()).unwrap();
+        let leaf = X509::from_pem(&fs::read(pki_path.join("certs/leaf.crt")).unwrap()).unwrap();
+
+        assert!(intermediate.verify(&root_cert.public_key().unwrap()).unwrap());
+        assert!(leaf.verify(&intermediate.public_key().unwrap()).unwrap());
+
+        Pkimgr::new(configuration, base_path.clone()).load(&name, None).unwrap();
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+    #[test]
+    fn test_external_issuer_only_on_root() {
+        let spec = Certificate {
+            cname: "Root".to_string(),
+            subcerts: vec![Certificate { cname: "Sub".to_string(), ca: true, external_issuer: true, ..Certificate::default() }],
+            ..Certificate::default()
+        };
+
+        assert!(spec.validate().is_err());
+    }
+}
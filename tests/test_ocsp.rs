@@ -0,0 +1,237 @@
+mod ocsp_tests {
+    use std::{
+        io::{Read, Write},
+        net::{Shutdown, TcpStream},
+        thread,
+        time::Duration
+    };
+
+    use openssl::{
+        base64,
+        hash::MessageDigest,
+        ocsp::{OcspCertId, OcspCertStatus, OcspFlag, OcspRequest, OcspResponse, OcspResponseStatus},
+        stack::Stack,
+        x509::{store::X509StoreBuilder, CrlStatus}
+    };
+    use pkimgr::{
+        certificates::{crl::RevocationReason, Certificate, Profile},
+        key::Key,
+        pki::Pki,
+        responder::OcspResponder,
+        Configuration, DEFAULT_CONFIGURATION
+    };
+
+    fn create_pki() -> Pki {
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let mut pki = Pki::new(&"ocsp".to_string(), configuration);
+        let key = || Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let spec = |cname: &str| Certificate { cname: cname.to_string(), ..Certificate::default() };
+        let root = "Root".to_string();
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_certificate(&spec("good"), &root, key()).unwrap();
+        pki.add_certificate(&spec("revoked"), &root, key()).unwrap();
+        pki.add_certificate(
+            &Certificate { profile: Some(Profile::OcspSigning), ..spec("responder") },
+            &root,
+            key()
+        ).unwrap();
+        pki.revoke(&"revoked".to_string(), RevocationReason::KeyCompromise).unwrap();
+
+        pki
+    }
+
+    fn query(pki: &Pki, responders: Vec<String>, name: &str) -> OcspCertStatus {
+        let (cert, _) = pki.get_entry(&name.to_string()).unwrap();
+        let (root, _) = pki.get_entry(&"Root".to_string()).unwrap();
+        let cert_id = || OcspCertId::from_cert(MessageDigest::sha1(), cert, root).unwrap();
+
+        let mut request = OcspRequest::new().unwrap();
+        request.add_id(cert_id()).unwrap();
+
+        let responder = OcspResponder::new(pki, responders, 1).unwrap();
+        let response = OcspResponse::from_der(&responder.respond(&request.to_der().unwrap())).unwrap();
+        assert_eq!(response.status(), OcspResponseStatus::SUCCESSFUL);
+
+        let basic = response.basic().unwrap();
+        let mut store = X509StoreBuilder::new().unwrap();
+        store.add_cert(root.clone()).unwrap();
+        let mut certs = Stack::new().unwrap();
+        certs.push(root.clone()).unwrap();
+        basic.verify(&certs, &store.build(), OcspFlag::empty()).unwrap();
+
+        basic.find_status(&cert_id()).unwrap().status
+    }
+
+    #[test]
+    fn test_ocsp_status() {
+        let pki = create_pki();
+
+        assert_eq!(query(&pki, vec![], "good"), OcspCertStatus::GOOD);
+        assert_eq!(query(&pki, vec![], "revoked"), OcspCertStatus::REVOKED);
+    }
+
+    #[test]
+    fn test_ocsp_renewed_serial() {
+        let mut pki = create_pki();
+        let good = "good".to_string();
+        let previous = pki.get_cert(&good).unwrap().clone();
+
+        pki.renew(&good, None).unwrap();
+
+        // The previous certificate was issued and not revoked
+        let (root, _) = pki.get_entry(&"Root".to_string()).unwrap();
+        let cert_id = || OcspCertId::from_cert(MessageDigest::sha1(), &previous, root).unwrap();
+        let mut request = OcspRequest::new().unwrap();
+        request.add_id(cert_id()).unwrap();
+
+        let responder = OcspResponder::new(&pki, vec![], 1).unwrap();
+        let response = OcspResponse::from_der(&responder.respond(&request.to_der().unwrap())).unwrap();
+
+        assert_eq!(response.basic().unwrap().find_status(&cert_id()).unwrap().status, OcspCertStatus::GOOD);
+        assert_eq!(query(&pki, vec![], "good"), OcspCertStatus::GOOD);
+    }
+
+    #[test]
+    fn test_ocsp_delegated_responder() {
+        let pki = create_pki();
+
+        assert_eq!(query(&pki, vec!["responder".to_string()], "good"), OcspCertStatus::GOOD);
+        // Only OCSP signing certificates issued by an authority can answer for it
+        assert!(OcspResponder::new(&pki, vec!["good".to_string()], 1).is_err());
+        assert!(OcspResponder::new(&pki, vec!["Root".to_string()], 1).is_err());
+    }
+
+    #[test]
+    fn test_ocsp_malformed_request() {
+        let pki = create_pki();
+        let responder = OcspResponder::new(&pki, vec![], 1).unwrap();
+        let response = OcspResponse::from_der(&responder.respond(b"garbage")).unwrap();
+
+        assert_eq!(response.status(), OcspResponseStatus::MALFORMED_REQUEST);
+    }
+
+    #[test]
+    fn test_retired_authority_key() {
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let mut pki = Pki::new(&"ocsp".to_string(), configuration);
+        let key = || Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let spec = |cname: &str| Certificate { cname: cname.to_string(), ..Certificate::default() };
+        let intermediate = "Intermediate".to_string();
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&"Root".to_string()), key()).unwrap();
+        pki.add_certificate(&spec("revoked"), &intermediate, key()).unwrap();
+        pki.add_certificate(&spec("good"), &intermediate, key()).unwrap();
+
+        let (old_intermediate, old_key) = pki.get_entry(&intermediate).unwrap().clone();
+
+        pki.rekey(&intermediate, key(), None, false).unwrap();
+        pki.revoke(&"revoked".to_string(), RevocationReason::KeyCompromise).unwrap();
+
+        // The retired key signs a CRL of its own, listing the certificates it issued
+        let crls = pki.retired_crls(&intermediate, 7).unwrap();
+        let (revoked, _) = pki.get_entry(&"revoked".to_string()).unwrap();
+
+        assert_eq!(crls.len(), 1);
+        assert!(crls[0].1.verify(&old_key.to_public_pkey().unwrap()).unwrap());
+        assert!(matches!(crls[0].1.get_by_serial(revoked.serial_number()), CrlStatus::Revoked(_)));
+
+        // And answers OCSP requests for them
+        let (root, _) = pki.get_entry(&"Root".to_string()).unwrap();
+        let status = |name: &str| {
+            let (cert, _) = pki.get_entry(&name.to_string()).unwrap();
+            let cert_id = || OcspCertId::from_cert(MessageDigest::sha1(), cert, &old_intermediate).unwrap();
+            let mut request = OcspRequest::new().unwrap();
+            request.add_id(cert_id()).unwrap();
+
+            let responder = OcspResponder::new(&pki, vec![], 1).unwrap();
+            let response = OcspResponse::from_der(&responder.respond(&request.to_der().unwrap())).unwrap();
+            assert_eq!(response.status(), OcspResponseStatus::SUCCESSFUL);
+
+            let basic = response.basic().unwrap();
+            let mut store = X509StoreBuilder::new().unwrap();
+            store.add_cert(root.clone()).unwrap();
+            let mut certs = Stack::new().unwrap();
+            certs.push(old_intermediate.clone()).unwrap();
+            basic.verify(&certs, &store.build(), OcspFlag::empty()).unwrap();
+
+            basic.find_status(&cert_id()).unwrap().status
+        };
+
+        assert_eq!(status("revoked"), OcspCertStatus::REVOKED);
+        assert_eq!(status("good"), OcspCertStatus::GOOD);
+    }
+
+    /// Serve the PKI OCSP responder on a local port, for the life of the test process
+    fn serve(pki: &'static Pki, port: u16) -> String {
+        let listen = format!("127.0.0.1:{}", port);
+        let address = listen.clone();
+
+        thread::spawn(move || OcspResponder::new(pki, vec![], 1).unwrap().serve(&listen));
+
+        for _ in 0..50 {
+            if TcpStream::connect(&address).is_ok() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        address
+    }
+
+    /// Status line of the answer to a raw HTTP request
+    fn http(address: &str, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut answer = vec![];
+
+        stream.write_all(request).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        stream.read_to_end(&mut answer).unwrap();
+
+        String::from_utf8_lossy(&answer).lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn test_ocsp_get_with_path() {
+        let pki: &'static Pki = Box::leak(Box::new(create_pki()));
+        let address = serve(pki, 40000 + (std::process::id() % 10000) as u16);
+        let (cert, _) = pki.get_entry(&"good".to_string()).unwrap();
+        let (root, _) = pki.get_entry(&"Root".to_string()).unwrap();
+        let mut request = OcspRequest::new().unwrap();
+
+        request.add_id(OcspCertId::from_cert(MessageDigest::sha1(), cert, root).unwrap()).unwrap();
+
+        let encoded = base64::encode_block(&request.to_der().unwrap()).replace('/', "%2F").replace('+', "%2B");
+
+        for path in [format!("/{}", encoded), format!("/ocsp/{}", encoded)] {
+            let status = http(&address, format!("GET {} HTTP/1.1\r\nHost: ocsp\r\n\r\n", path).as_bytes());
+
+            assert_eq!(status, "HTTP/1.1 200 OK");
+        }
+    }
+
+    #[test]
+    fn test_ocsp_post_too_large() {
+        let pki: &'static Pki = Box::leak(Box::new(create_pki()));
+        let address = serve(pki, 50000 + (std::process::id() % 10000) as u16);
+        let request = "POST / HTTP/1.1\r\nContent-Type: application/ocsp-request\r\nContent-Length: 100000000\r\n\r\n";
+
+        assert_eq!(http(&address, request.as_bytes()), "HTTP/1.1 413 Payload Too Large");
+    }
+
+    #[test]
+    fn test_ocsp_header_too_large() {
+        let pki: &'static Pki = Box::leak(Box::new(create_pki()));
+        let address = serve(pki, 30000 + (std::process::id() % 10000) as u16);
+        // A header line never ending, as long as the request limit
+        let mut request = b"GET / HTTP/1.1\r\nX-Padding: ".to_vec();
+
+        request.resize(64 * 1024, b'a');
+
+        assert_eq!(http(&address, &request), "HTTP/1.1 413 Payload Too Large");
+        // Or closed before their end
+        assert_eq!(http(&address, b"GET / HTTP/1.1\r\nHost: ocsp"), "HTTP/1.1 400 Bad Request");
+    }
+}
@@ -0,0 +1,28 @@
+mod pkcs12_tests {
+    use openssl::pkcs12::Pkcs12;
+    use pkimgr::{certificates::Certificate, key::Key, pki::Pki, Configuration, DEFAULT_CONFIGURATION};
+
+    #[test]
+    fn test_pkcs12_bundle() {
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let mut pki = Pki::new(&"pkcs12".to_string(), configuration);
+        let key = || Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let spec = |cname: &str| Certificate { cname: cname.to_string(), ..Certificate::default() };
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&"Root".to_string()), key()).unwrap();
+        pki.add_certificate(&spec("web"), &"Intermediate".to_string(), key()).unwrap();
+
+        let der = pki.pkcs12(&"web".to_string(), "s3cret", Some("Web server")).unwrap();
+        let pkcs12 = Pkcs12::from_der(&der).unwrap();
+
+        assert!(pkcs12.parse2("wrong").is_err());
+
+        let parsed = pkcs12.parse2("s3cret").unwrap();
+        let (web, web_key) = pki.get_entry(&"web".to_string()).unwrap();
+
+        assert_eq!(parsed.cert.unwrap().to_der().unwrap(), web.to_der().unwrap());
+        assert!(parsed.pkey.unwrap().public_eq(&web_key.to_private_pkey().unwrap()));
+        assert_eq!(parsed.ca.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,251 @@
+mod pki_load_tests {
+    use std::{env, fs, io::Write, path::PathBuf};
+
+    use openssl::x509::X509;
+    use pkimgr::{certificates::{crl::RevocationReason, Certificate}, cli::Pkimgr, Configuration, DEFAULT_CONFIGURATION};
+
+    const SPEC: &str = r#"
+    {
+        "pki_name": "load",
+        "root": {
+            "cname": "Root",
+            "curve": "secp384r1",
+            "subcerts": [
+                {
+                    "cname": "Intermediate",
+                    "curve": "secp384r1",
+                    "subcerts": [
+                        { "cname": "leaf", "curve": "secp256k1", "subcerts": [] }
+                    ]
+                }
+            ]
+        }
+    }"#;
+
+    fn create_pki(test_name: &str) -> (PathBuf, Configuration) {
+        let base_path = env::temp_dir().join(format!("pkimgr-{}-{}", test_name, std::process::id()));
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let spec_path = base_path.join("spec.json");
+
+        fs::create_dir_all(&base_path).unwrap();
+        fs::File::create(&spec_path).unwrap().write_all(SPEC.as_bytes()).unwrap();
+
+        Pkimgr::new(configuration.clone(), base_path.clone())
+            .create_from_file(fs::File::open(&spec_path).unwrap())
+            .unwrap();
+
+        (base_path, configuration)
+    }
+
+    #[test]
+    fn test_load_saved_pki() {
+        let (base_path, configuration) = create_pki("load");
+        let mut manager = Pkimgr::new(configuration, base_path.clone());
+
+        manager.load(&"load".to_string(), None).unwrap();
+
+        assert_eq!(manager.get_pki(), vec!["load"]);
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+    #[test]
+    fn test_load_rejects_mismatching_files() {
+        let (base_path, configuration) = create_pki("mismatch");
+        let pki_path = base_path.join("load");
+
+        fs::copy(pki_path.join("private/Root.pem"), pki_path.join("private/leaf.pem")).unwrap();
+
+        let mut manager = Pkimgr::new(configuration, base_path.clone());
+
+        assert!(manager.load(&"load".to_string(), None).is_err());
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+
+    #[test]
+    fn test_issue_on_saved_pki() {
+        let (base_path, configuration) = create_pki("issue");
+        let root_key = fs::read(base_path.join("load/private/Root.pem")).unwrap();
+        let cert = Certificate {
+            cname: "api.internal".to_string(),
+            curve: Some("secp384r1".to_string()),
+            ..Certificate::default()
+        };
+
+        Pkimgr::new(configuration.clone(), base_path.clone())
+            .issue(&"load".to_string(), &"Intermediate".to_string(), cert.clone())
+            .unwrap();
+
+        assert!(base_path.join("load/certs/api.internal.crt").exists());
+        assert_eq!(fs::read(base_path.join("load/private/Root.pem")).unwrap(), root_key);
+
+        let mut manager = Pkimgr::new(configuration, base_path.clone());
+
+        manager.load(&"load".to_string(), None).unwrap();
+        assert!(manager.issue(&"load".to_string(), &"Intermediate".to_string(), cert).is_err());
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+
+    #[test]
+    fn test_issue_validity() {
+        let (base_path, configuration) = create_pki("validity");
+        let mut manager = Pkimgr::new(configuration, base_path.clone());
+        let pki = "load".to_string();
+        let issuer = "Intermediate".to_string();
+        let spec = |cname: &str| Certificate {
+            cname: cname.to_string(),
+            curve: Some("secp384r1".to_string()),
+            ..Certificate::default()
+        };
+
+        manager.issue(&pki, &issuer, Certificate { validity: Some(90), ..spec("short") }).unwrap();
+
+        let short = X509::from_pem(&fs::read(base_path.join("load/certs/short.crt")).unwrap()).unwrap();
+        let days = short.not_before().diff(short.not_after()).unwrap().days;
+
+        assert_eq!(days, 90);
+
+        // Outliving the issuer is refused unless explicitly allowed
+        assert!(manager.issue(&pki, &issuer, Certificate { validity: Some(3650), ..spec("long") }).is_err());
+        manager.issue(&pki, &issuer, Certificate { validity: Some(3650), outlive_issuer: true, ..spec("long") }).unwrap();
+
+        let fixed = Certificate {
+            not_before: Some("2024-01-01".to_string()),
+            not_after: Some("20240401120000Z".to_string()),
+            ..spec("fixed")
+        };
+
+        manager.issue(&pki, &issuer, fixed).unwrap();
+
+        let fixed = X509::from_pem(&fs::read(base_path.join("load/certs/fixed.crt")).unwrap()).unwrap();
+
+        assert_eq!(fixed.not_before().to_string(), "Jan  1 00:00:00 2024 GMT");
+        assert_eq!(fixed.not_after().to_string(), "Apr  1 12:00:00 2024 GMT");
+
+        let inverted = Certificate { not_after: Some("2020-01-01".to_string()), ..spec("inverted") };
+
+        assert!(manager.issue(&pki, &issuer, inverted).is_err());
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+    #[test]
+    fn test_revoke_and_crl_results() {
+        let (base_path, configuration) = create_pki("results");
+        let mut manager = Pkimgr::new(configuration, base_path.clone());
+        let pki = "load".to_string();
+        let serial = manager.show(&pki, &"leaf".to_string()).unwrap().serial;
+        let revocation = manager.revoke(&pki, &"leaf".to_string(), RevocationReason::Superseded).unwrap();
+
+        assert_eq!(Some(revocation.serial), serial);
+
+        let written = manager.generate_crl(&pki, Some(&"Intermediate".to_string()), 7).unwrap();
+
+        assert_eq!(written, [
+            base_path.join("load/crl/Intermediate.crl"),
+            base_path.join("load/crl/Intermediate.crl.pem")
+        ]);
+        assert!(written.iter().all(|path| path.exists()));
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+    #[test]
+    fn test_retired_keys_saved() {
+        let (base_path, configuration) = create_pki("retired");
+        let pki = "load".to_string();
+        let intermediate = "Intermediate".to_string();
+
+        Pkimgr::new(configuration.clone(), base_path.clone())
+            .rekey(&pki, &intermediate, None, None, None, false)
+            .unwrap();
+
+        // Reloaded, the retired key still signs a CRL for the certificates issued before
+        let written = Pkimgr::new(configuration, base_path.clone())
+            .generate_crl(&pki, Some(&intermediate), 7)
+            .unwrap();
+
+        assert_eq!(written.len(), 4);
+        assert!(written.iter().all(|path| path.exists()));
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_keys_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (base_path, configuration) = create_pki("permissions");
+        let private = base_path.join("load/private");
+        let mode = |path: PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        assert_eq!(mode(private.clone()), 0o700);
+        assert_eq!(mode(private.join("leaf.pem")), 0o600);
+
+        // A rewritten key file is restricted again
+        fs::set_permissions(private.join("leaf.pem"), fs::Permissions::from_mode(0o644)).unwrap();
+        Pkimgr::new(configuration, base_path.clone())
+            .rekey(&"load".to_string(), &"leaf".to_string(), None, None, None, false)
+            .unwrap();
+
+        assert_eq!(mode(private.join("leaf.pem")), 0o600);
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+    #[test]
+    fn test_create_rejects_invalid_spec() {
+        let base_path = env::temp_dir().join(format!("pkimgr-invalid-{}", std::process::id()));
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let spec_path = base_path.join("spec.json");
+        let specs = [
+            (SPEC.replace("secp256k1", "p265"), "leaf: curve: Unknown curve: p265"),
+            (SPEC.replace(r#""curve": "secp256k1""#, r#""curve": "secp256k1", "keylen": 2048"#), "leaf: keylen"),
+            (SPEC.replace("leaf", "Intermediate"), "Intermediate: cname: duplicated"),
+            (SPEC.replace(r#""subcerts": [] }"#, r#""subcerts": [], "san": {"ip": ["10.0.0.256"]} }"#), "leaf: san: invalid IP address"),
+            (SPEC.replace(r#""subcerts": [] }"#, r#""subcerts": [], "san": {"email": ["ops"]} }"#), "leaf: san: invalid email address"),
+            (SPEC.replace(r#""subcerts": [] }"#, r#""subcerts": [], "san": {"uri": ["api internal"]} }"#), "leaf: san: invalid URI"),
+        ];
+
+        fs::create_dir_all(&base_path).unwrap();
+
+        for (spec, error) in specs {
+            fs::write(&spec_path, spec).unwrap();
+
+            let err = Pkimgr::new(configuration.clone(), base_path.clone())
+                .create_from_file(fs::File::open(&spec_path).unwrap())
+                .err()
+                .unwrap();
+
+            assert!(err.to_string().contains(error), "{}", err);
+            assert!(!base_path.join("load").exists());
+        }
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+
+    #[test]
+    fn test_create_fails_on_refused_entry() {
+        let base_path = env::temp_dir().join(format!("pkimgr-refused-{}", std::process::id()));
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let spec_path = base_path.join("spec.json");
+
+        fs::create_dir_all(&base_path).unwrap();
+        fs::write(&spec_path, SPEC.replace(r#""curve": "secp256k1""#, r#""curve": "secp256k1", "validity": 36500"#)).unwrap();
+
+        let err = Pkimgr::new(configuration, base_path.clone())
+            .create_from_file(fs::File::open(&spec_path).unwrap())
+            .err()
+            .unwrap();
+
+        assert!(err.to_string().contains("after its issuer"), "{}", err);
+        assert!(!base_path.join("load").exists());
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+mod policies_tests {
+    use pkimgr::{
+        certificates::{
+            policies::{Policy, PolicyConstraints},
+            Certificate
+        }
+    };
+
+    use crate::common::{key, new_pki, spec, text};
+
+    const POLICY: &str = "1.3.6.1.4.1.55555.1";
+
+    fn policy(oid: &str) -> Policy {
+        Policy {
+            oid: oid.to_string(),
+            cps: vec!["http://pki.example.com/cps".to_string()],
+            notice: Some("Issued for internal use only".to_string())
+        }
+    }
+
+    #[test]
+    fn test_policies_inherited() {
+        let mut pki = new_pki("policies");
+        let root = "Root".to_string();
+        let intermediate = Certificate {
+            policies: vec![policy(POLICY)],
+            inherit_policies: true,
+            policy_constraints: Some(PolicyConstraints { require_explicit_policy: Some(0), inhibit_policy_mapping: None }),
+            inhibit_any_policy: Some(0),
+            ..spec("Intermediate")
+        };
+        let other = Certificate { policies: vec![policy(POLICY)], ..spec("Other") };
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&intermediate, Some(&root), key()).unwrap();
+        pki.add_authority(&other, Some(&root), key()).unwrap();
+        pki.add_certificate(&spec("web"), &"Intermediate".to_string(), key()).unwrap();
+        pki.add_certificate(&spec("internal"), &"Other".to_string(), key()).unwrap();
+
+        let intermediate = text(&pki, "Intermediate");
+
+        assert!(intermediate.contains(&format!("Policy: {}", POLICY)));
+        assert!(intermediate.contains("CPS: http://pki.example.com/cps"));
+        assert!(intermediate.contains("Explicit Text: Issued for internal use only"));
+        assert!(intermediate.contains("X509v3 Policy Constraints: critical"));
+        assert!(intermediate.contains("Require Explicit Policy:0"));
+        assert!(intermediate.contains("X509v3 Inhibit Any Policy: critical"));
+        assert!(text(&pki, "web").contains(&format!("Policy: {}", POLICY)));
+        assert!(!text(&pki, "web").contains("Policy Constraints"));
+        assert!(!text(&pki, "internal").contains("Certificate Policies"));
+        assert!(!text(&pki, "Root").contains("Certificate Policies"));
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_policies_validation() {
+        let root = |subcert: Certificate| Certificate { subcerts: vec![subcert], ..spec("Root") };
+        let with_policy = |policy: Policy| root(Certificate { policies: vec![policy], ..spec("web") }).validate();
+
+        assert!(with_policy(policy(POLICY)).is_ok());
+        assert!(with_policy(policy("not.an.oid")).is_err());
+        assert!(with_policy(Policy { notice: Some("x".repeat(201)), ..policy(POLICY) }).is_err());
+        assert!(with_policy(Policy { cps: vec!["cps.html".to_string()], ..policy(POLICY) }).is_err());
+
+        let empty_constraints = Certificate {
+            ca: true,
+            policy_constraints: Some(PolicyConstraints::default()),
+            ..spec("Intermediate")
+        };
+
+        assert!(root(empty_constraints).validate().is_err());
+        assert!(root(Certificate { inhibit_any_policy: Some(0), ..spec("web") }).validate().is_err());
+    }
+}
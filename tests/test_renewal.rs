@@ -0,0 +1,75 @@
+mod renewal_tests {
+    use pkimgr::{certificates::Certificate, key::Key, pki::Pki, Configuration, DEFAULT_CONFIGURATION};
+
+    fn key(curve: &str) -> Key {
+        Key::new(None, Some(curve.to_string())).unwrap()
+    }
+
+    fn create_pki() -> Pki {
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let mut pki = Pki::new(&"renewal".to_string(), configuration);
+        let spec = |cname: &str| Certificate { cname: cname.to_string(), ..Certificate::default() };
+
+        pki.add_authority(&spec("Root"), None, key("secp384r1")).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&"Root".to_string()), key("secp384r1")).unwrap();
+        pki.add_certificate(&spec("web"), &"Intermediate".to_string(), key("secp384r1")).unwrap();
+
+        pki
+    }
+
+    fn serial(pki: &Pki, name: &str) -> String {
+        pki.get_cert(&name.to_string()).unwrap().serial_number().to_bn().unwrap().to_hex_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_rekey_leaf() {
+        let mut pki = create_pki();
+        let web = "web".to_string();
+        let previous = serial(&pki, "web");
+
+        pki.renew(&web, None).unwrap();
+        pki.rekey(&web, key("prime256v1"), None, false).unwrap();
+
+        let info = pki.entry_info(&web).unwrap();
+
+        assert_eq!(info.curve.as_deref(), Some("prime256v1"));
+        assert_eq!(info.history.len(), 2);
+        assert_eq!(info.history[0].serial, previous);
+        assert!(!info.history[0].rekeyed);
+        assert!(info.history[1].rekeyed);
+        assert!(pki.retired.is_empty());
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rekey_authority_retires_previous_certificate() {
+        let mut pki = create_pki();
+        let name = "Intermediate".to_string();
+        let web = serial(&pki, "web");
+
+        pki.rekey(&name, key("secp384r1"), None, false).unwrap();
+
+        assert_eq!(serial(&pki, "web"), web);
+        assert_eq!(pki.retired.get(&name).map(Vec::len), Some(1));
+        assert_eq!(pki.get_chain(&"web".to_string()).unwrap()[0].to_der().unwrap(), pki.retired[&name][0].0.to_der().unwrap());
+        assert!(pki.verify().unwrap().is_empty());
+
+        // Re-keying the root reissues the intermediate, along with its retired certificate
+        pki.rekey(&"Root".to_string(), key("secp384r1"), None, true).unwrap();
+
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rekey_reissues_subordinates() {
+        let mut pki = create_pki();
+        let web = serial(&pki, "web");
+
+        pki.rekey(&"Intermediate".to_string(), key("secp384r1"), None, true).unwrap();
+
+        assert_ne!(serial(&pki, "web"), web);
+        assert_eq!(pki.entry_info(&"web".to_string()).unwrap().history[0].serial, web);
+        assert!(pki.retired.is_empty());
+        assert!(pki.verify().unwrap().is_empty());
+    }
+}
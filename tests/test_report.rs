@@ -0,0 +1,76 @@
+mod report_tests {
+    use pkimgr::{
+        certificates::{crl::RevocationReason, Certificate},
+        key::Key,
+        pki::Pki,
+        report::EntryKind,
+        Configuration, DEFAULT_CONFIGURATION
+    };
+
+    fn create_pki() -> Pki {
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let mut pki = Pki::new(&"report".to_string(), configuration);
+        let key = || Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let spec = |cname: &str| Certificate { cname: cname.to_string(), ..Certificate::default() };
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&"Root".to_string()), key()).unwrap();
+        pki.add_certificate(&spec("web"), &"Intermediate".to_string(), key()).unwrap();
+
+        pki
+    }
+
+    #[test]
+    fn test_entries() {
+        let mut pki = create_pki();
+
+        pki.revoke(&"web".to_string(), RevocationReason::Superseded).unwrap();
+
+        let entries = pki.entries().unwrap();
+        let kinds: Vec<(&str, EntryKind)> = entries.iter().map(|entry| (entry.cname.as_str(), entry.kind)).collect();
+
+        assert_eq!(kinds, vec![
+            ("Root", EntryKind::Root),
+            ("Intermediate", EntryKind::Authority),
+            ("web", EntryKind::Certificate)
+        ]);
+        assert_eq!(entries[2].issuer.as_deref(), Some("Intermediate"));
+        assert_eq!(entries[2].revoked, Some(RevocationReason::Superseded));
+        assert_eq!(entries[1].revoked, None);
+        assert!(pki.entry_info(&"missing".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_renew_keeps_key_and_chain() {
+        let mut pki = create_pki();
+        let name = "Intermediate".to_string();
+        let (before, key) = pki.get_entry(&name).unwrap().clone();
+
+        pki.renew(&name, Some(30)).unwrap();
+
+        let (after, renewed_key) = pki.get_entry(&name).unwrap();
+
+        assert_ne!(before.serial_number().to_bn().unwrap(), after.serial_number().to_bn().unwrap());
+        assert_eq!(renewed_key.to_pem().unwrap(), key.to_pem().unwrap());
+        assert!(after.not_after() < before.not_after());
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_verify_reports_expired() {
+        let mut pki = create_pki();
+        let spec = Certificate {
+            cname: "expired".to_string(),
+            not_before: Some("2020-01-01".to_string()),
+            not_after: Some("2020-02-01".to_string()),
+            ..Certificate::default()
+        };
+
+        pki.add_certificate(&spec, &"Intermediate".to_string(), Key::new(None, Some("secp384r1".to_string())).unwrap()).unwrap();
+
+        let failures = pki.verify().unwrap();
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].cname, "expired");
+    }
+}
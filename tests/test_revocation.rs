@@ -0,0 +1,86 @@
+mod revocation_tests {
+    use pkimgr::{
+        certificates::{crl::RevocationReason, der, Certificate},
+        key::Key,
+        pki::Pki,
+        Configuration, DEFAULT_CONFIGURATION
+    };
+
+    fn create_pki() -> Pki {
+        let configuration: Configuration = serde_json::from_str(DEFAULT_CONFIGURATION).unwrap();
+        let mut pki = Pki::new(&"crl".to_string(), configuration);
+        let key = || Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let spec = |cname: &str| Certificate { cname: cname.to_string(), ..Certificate::default() };
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_certificate(&spec("web"), &"Root".to_string(), key()).unwrap();
+
+        pki
+    }
+
+    /// Identifier and value of every CRL extension
+    fn crl_extensions(crl_der: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let crl = der::Der::new(crl_der).expect(der::TAG_SEQUENCE).unwrap();
+        let mut tbs = crl.children().expect(der::TAG_SEQUENCE).unwrap().children();
+        let mut extensions = vec![];
+
+        while let Some(element) = tbs.read() {
+            if element.tag != 0xa0 {
+                continue;
+            }
+
+            let mut list = element.children().expect(der::TAG_SEQUENCE).unwrap().children();
+
+            while let Some(extension) = list.read() {
+                let mut fields = extension.children();
+                let oid = fields.expect(der::TAG_OID).unwrap().raw.to_vec();
+                fields.read_optional(0x01);
+                let value = fields.expect(der::TAG_OCTET_STRING).unwrap().content.to_vec();
+
+                extensions.push((oid, value));
+            }
+        }
+
+        extensions
+    }
+
+    #[test]
+    fn test_crl_lists_revoked_serials() {
+        let mut pki = create_pki();
+        let root = "Root".to_string();
+
+        pki.revoke(&"web".to_string(), RevocationReason::KeyCompromise).unwrap();
+        assert!(pki.revoke(&"web".to_string(), RevocationReason::Superseded).is_err());
+        assert!(pki.revoke(&root, RevocationReason::Superseded).is_err());
+
+        let crl = pki.crl(&root, 7).unwrap();
+        let (web, _) = pki.get_entry(&"web".to_string()).unwrap();
+        let (root_cert, _) = pki.get_entry(&root).unwrap();
+        let revoked = crl.get_revoked().unwrap();
+
+        assert_eq!(revoked.len(), 1);
+        assert_eq!(
+            revoked.get(0).unwrap().serial_number().to_bn().unwrap(),
+            web.serial_number().to_bn().unwrap()
+        );
+        assert!(crl.verify(&root_cert.public_key().unwrap()).unwrap());
+
+        let extensions = crl_extensions(&crl.to_der().unwrap());
+        let key_id = root_cert.subject_key_id().unwrap().as_slice();
+
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions[0], (der::oid("2.5.29.20").unwrap(), der::tlv(der::TAG_INTEGER, &[1])));
+        assert_eq!(extensions[1], (der::oid("2.5.29.35").unwrap(), der::sequence(&[der::implicit(0, key_id)])));
+    }
+
+    #[test]
+    fn test_crl_number_increases() {
+        let mut pki = create_pki();
+        let root = "Root".to_string();
+
+        pki.crl(&root, 7).unwrap();
+        pki.crl(&root, 7).unwrap();
+
+        assert_eq!(pki.revocations[&root].crl_number, 2);
+    }
+}
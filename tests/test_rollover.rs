@@ -0,0 +1,76 @@
+mod common;
+
+mod rollover_tests {
+    use std::cmp::Ordering;
+
+    use openssl::asn1::Asn1OctetStringRef;
+    use pkimgr::pki::Pki;
+
+    use crate::common::{key, new_pki, spec};
+
+    fn create_pki() -> Pki {
+        let mut pki = new_pki("rollover");
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&"Root".to_string()), key()).unwrap();
+        pki.add_certificate(&spec("web"), &"Intermediate".to_string(), key()).unwrap();
+
+        pki
+    }
+
+    #[test]
+    fn test_cross_certificates() {
+        let mut pki = create_pki();
+        let rollover = pki.rollover(key(), None, false).unwrap();
+        let old_key = rollover.old_root.public_key().unwrap();
+        let new_key = rollover.new_root.public_key().unwrap();
+
+        assert!(rollover.new_root.verify(&new_key).unwrap());
+        assert!(rollover.old_with_new.verify(&new_key).unwrap());
+        assert!(rollover.old_with_new.public_key().unwrap().public_eq(&old_key));
+        assert!(rollover.new_with_old.verify(&old_key).unwrap());
+        assert!(rollover.new_with_old.public_key().unwrap().public_eq(&new_key));
+        assert_eq!(
+            rollover.new_root.subject_name().try_cmp(rollover.old_root.subject_name()).unwrap(),
+            Ordering::Equal
+        );
+        assert!(rollover.new_with_old.not_after() <= rollover.old_root.not_after());
+
+        // Cross-certificates keep the identifiers of the roots, for chains to go through them
+        let key_id = |id: Option<&Asn1OctetStringRef>| id.unwrap().as_slice().to_vec();
+
+        assert_ne!(key_id(rollover.old_root.subject_key_id()), key_id(rollover.new_root.subject_key_id()));
+        assert_eq!(key_id(rollover.old_with_new.subject_key_id()), key_id(rollover.old_root.subject_key_id()));
+        assert_eq!(key_id(rollover.old_with_new.authority_key_id()), key_id(rollover.new_root.subject_key_id()));
+        assert_eq!(key_id(rollover.new_with_old.subject_key_id()), key_id(rollover.new_root.subject_key_id()));
+        assert_eq!(key_id(rollover.new_with_old.authority_key_id()), key_id(rollover.old_root.subject_key_id()));
+        assert!(rollover.new_root.authority_key_id().is_none());
+    }
+
+    #[test]
+    fn test_intermediates_chain_to_either_root() {
+        let mut pki = create_pki();
+        let intermediate = "Intermediate".to_string();
+        let rollover = pki.rollover(key(), None, false).unwrap();
+        let root_of = |pki: &Pki| pki.get_chain(&intermediate).unwrap()[0].to_der().unwrap();
+
+        assert_eq!(root_of(&pki), rollover.old_root.to_der().unwrap());
+        assert!(pki.verify().unwrap().is_empty());
+
+        pki.renew(&intermediate, None).unwrap();
+
+        assert_eq!(root_of(&pki), rollover.new_root.to_der().unwrap());
+        assert!(pki.verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rollover_reissuing_subordinates() {
+        let mut pki = create_pki();
+        let rollover = pki.rollover(key(), None, true).unwrap();
+        let chain = pki.get_chain(&"web".to_string()).unwrap();
+
+        assert_eq!(chain[1].to_der().unwrap(), rollover.new_root.to_der().unwrap());
+        assert_eq!(pki.retired[&"Root".to_string()].len(), 1);
+        assert!(pki.verify().unwrap().is_empty());
+    }
+}
@@ -1,6 +1,86 @@
 mod rsa_keygen_tests {
+    use openssl::{ec::{EcGroup, EcKey}, nid::Nid, pkey::PKey, symm::Cipher};
+    use pkimgr::{certificates::signature::{Digest, Signature}, key::{Curve, Key, KeyType}};
+
     #[test]
     fn test_generate_key() {
         assert_eq!(1, 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_encrypted_pem() {
+        let key = Key::new(Some(2048), None).unwrap();
+        let pem = key.to_encrypted_pem(b"s3cret").unwrap();
+
+        assert!(String::from_utf8_lossy(&pem).contains("ENCRYPTED PRIVATE KEY"));
+        assert!(Key::from_pem(&pem, None).is_err());
+        assert!(Key::from_pem(&pem, Some(b"wrong")).is_err());
+
+        let decrypted = Key::from_pem(&pem, Some(b"s3cret")).unwrap();
+
+        assert_eq!(decrypted.to_pem().unwrap(), key.to_pem().unwrap());
+        assert_eq!(Key::from_pem(&key.to_pem().unwrap(), None).unwrap().rsa_len(), Some(2048));
+    }
+
+    #[test]
+    fn test_encrypted_pem_detection() {
+        let key = Key::new(None, Some("prime256v1".to_string())).unwrap();
+
+        // Traditional PEM encryption
+        let ec = match &key { Key::Ec(ec) => ec.clone(), _ => unreachable!() };
+        let pem = ec.private_key_to_pem_passphrase(Cipher::aes_256_cbc(), b"s3cret").unwrap();
+
+        assert!(Key::from_pem(&pem, None).is_err());
+        assert_eq!(Key::from_pem(&pem, Some(b"s3cret")).unwrap().to_pem().unwrap(), key.to_pem().unwrap());
+
+        // Text outside the PEM block mentioning encryption
+        let pem = [b"ENCRYPTED backup of the web key\n".to_vec(), key.to_pem().unwrap()].concat();
+
+        assert_eq!(Key::from_pem(&pem, None).unwrap().to_pem().unwrap(), key.to_pem().unwrap());
+    }
+
+    #[test]
+    fn test_curves() {
+        let curves = [
+            ("P-256", "prime256v1"),
+            ("ec", "prime256v1"),
+            ("secp384r1", "secp384r1"),
+            ("ed25519", "ed25519"),
+            ("Ed448", "ed448"),
+        ];
+
+        for (name, curve) in curves {
+            let key = Key::new(None, Some(name.to_string())).unwrap();
+            let reloaded = Key::from_pem(&key.to_pem().unwrap(), None).unwrap();
+
+            assert_eq!(key.curve().as_deref(), Some(curve));
+            assert_eq!(reloaded.curve().as_deref(), Some(curve));
+            assert_eq!(reloaded.rsa_len(), None);
+        }
+
+        assert!(Key::new(None, Some("p265".to_string())).is_err());
+        assert!(Key::new(Some(2048), Some("prime256v1".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_key_types() {
+        assert_eq!(KeyType::new(None, None), Ok(KeyType::Rsa(4096)));
+        assert_eq!(KeyType::new(None, Some("ec")), Ok(KeyType::Ec(Curve::Prime256v1)));
+        assert_eq!(KeyType::new(None, Some("Ed25519")), Ok(KeyType::Ed25519));
+        assert_eq!(KeyType::Ed448.curve_name().as_deref(), Some("ed448"));
+
+        // EdDSA algorithms are no curves
+        assert!("ed25519".parse::<Curve>().is_err());
+        assert_eq!(Curve::default(), Curve::Prime256v1);
+    }
+
+    #[test]
+    fn test_unsupported_curve() {
+        let group = EcGroup::from_curve_name(Nid::SECP224R1).unwrap();
+        let pem = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap().private_key_to_pem_pkcs8().unwrap();
+        let key = Key::from_pem(&pem, None).unwrap();
+
+        assert_eq!(key.curve(), None);
+        assert_eq!(Signature::for_key(&key).digest, Digest::Sha256);
+    }
+}
@@ -0,0 +1,176 @@
+mod common;
+
+mod serials_tests {
+    use std::{collections::BTreeMap, env, fs, io::Write};
+
+    use openssl::x509::X509;
+    use pkimgr::{
+        certificates::{
+            constraints::{NameConstraints, Subtrees},
+            serial::{SerialIndex, SerialStrategy},
+            AltNames, Certificate, PreviousCertificate
+        },
+        cli::Pkimgr,
+        pki::Pki
+    };
+
+    use crate::common::{configuration, key, new_pki, spec};
+
+    const SPEC: &str = r#"
+    {
+        "pki_name": "serials",
+        "root": {
+            "cname": "Root",
+            "curve": "secp384r1",
+            "subcerts": [
+                {
+                    "cname": "Intermediate",
+                    "curve": "secp384r1",
+                    "serials": "sequential",
+                    "subcerts": [
+                        { "cname": "web", "curve": "secp384r1", "subcerts": [] },
+                        { "cname": "api", "curve": "secp384r1", "subcerts": [] }
+                    ]
+                }
+            ]
+        }
+    }"#;
+
+    fn serial(cert: &X509) -> String {
+        cert.serial_number().to_bn().unwrap().to_hex_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_random_serials() {
+        let mut pki = new_pki("serials");
+        let root = "Root".to_string();
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+
+        for cname in ["web", "api", "mail"] {
+            pki.add_certificate(&spec(cname), &root, key()).unwrap();
+
+            let serial = pki.get_cert(&cname.to_string()).unwrap().serial_number().to_bn().unwrap();
+
+            // At most 20 octets once DER encoded, RFC 5280 section 4.1.2.2
+            assert_eq!(serial.num_bits(), 159);
+            assert!(!serial.is_negative());
+        }
+
+        assert_eq!(pki.serials[&root].issued.len(), 4);
+        assert!(pki.serials[&root].issued.contains(&serial(pki.get_cert(&root).unwrap())));
+    }
+
+    #[test]
+    fn test_sequential_serials() {
+        let mut pki = new_pki("serials");
+        let root = "Root".to_string();
+        let intermediate = "Intermediate".to_string();
+
+        let name_constraints = NameConstraints {
+            permitted: Subtrees { dns: vec!["example.com".to_string()], ..Subtrees::default() },
+            ..NameConstraints::default()
+        };
+        let root_spec = Certificate {
+            serials: Some(SerialStrategy::Sequential),
+            name_constraints: Some(name_constraints),
+            ..spec("Root")
+        };
+
+        pki.add_authority(&root_spec, None, key()).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&root), key()).unwrap();
+        pki.add_certificate(&spec("web"), &root, key()).unwrap();
+        pki.add_certificate(&spec("api"), &intermediate, key()).unwrap();
+
+        let cert = |pki: &Pki, name: &str| serial(pki.get_cert(&name.to_string()).unwrap());
+
+        assert_eq!(cert(&pki, "Root"), "01");
+        assert_eq!(cert(&pki, "Intermediate"), "02");
+        assert_eq!(cert(&pki, "web"), "03");
+        // The strategy is the one of the issuer
+        assert_ne!(cert(&pki, "api").len(), 2);
+
+        pki.renew(&"web".to_string(), None).unwrap();
+
+        assert_eq!(cert(&pki, "web"), "04");
+        assert_eq!(pki.serials[&root].counter, 4);
+
+        // A refused certificate leaves no gap
+        let outside = Certificate { san: Some(AltNames { dns: vec!["other.org".to_string()], ..AltNames::default() }), ..spec("other") };
+
+        assert!(pki.add_certificate(&outside, &root, key()).is_err());
+        pki.add_certificate(&spec("mail"), &root, key()).unwrap();
+
+        assert_eq!(cert(&pki, "mail"), "05");
+
+        // Serial numbers already used are skipped
+        let mut index = SerialIndex::default();
+        index.issued.insert("01".to_string());
+
+        assert_eq!(index.next(SerialStrategy::Sequential).unwrap().to_hex_str().unwrap().to_string(), "02");
+    }
+
+    #[test]
+    fn test_duplicated_serials() {
+        let mut pki = new_pki("serials");
+        let root = "Root".to_string();
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_certificate(&spec("web"), &root, key()).unwrap();
+        pki.add_certificate(&spec("api"), &root, key()).unwrap();
+        pki.index_serials().unwrap();
+
+        // A previous certificate of api with the serial number of web
+        let web = serial(pki.get_cert(&"web".to_string()).unwrap());
+        let api = pki.json.root.subcerts.iter_mut().find(|entry| entry.cname == "api").unwrap();
+
+        api.history.push(PreviousCertificate { serial: web, not_after: String::new(), rekeyed: false });
+
+        assert!(pki.index_serials().is_err());
+    }
+
+    #[test]
+    fn test_serials_persisted() {
+        let base_path = env::temp_dir().join(format!("pkimgr-serials-{}", std::process::id()));
+        let configuration = configuration();
+        let spec_path = base_path.join("spec.json");
+        let pki = "serials".to_string();
+        let intermediate = "Intermediate".to_string();
+        let serials_path = base_path.join("serials/serials.json");
+        let read_serials = || -> BTreeMap<String, SerialIndex> {
+            serde_json::from_slice(&fs::read(&serials_path).unwrap()).unwrap()
+        };
+        let read_cert = |name: &str| X509::from_pem(&fs::read(base_path.join(format!("serials/certs/{}.crt", name))).unwrap()).unwrap();
+
+        fs::create_dir_all(&base_path).unwrap();
+        fs::File::create(&spec_path).unwrap().write_all(SPEC.as_bytes()).unwrap();
+
+        Pkimgr::new(configuration.clone(), base_path.clone())
+            .create_from_file(fs::File::open(&spec_path).unwrap())
+            .unwrap();
+
+        assert_eq!(read_serials()[&intermediate].counter, 2);
+
+        Pkimgr::new(configuration.clone(), base_path.clone())
+            .issue(&pki, &intermediate, Certificate { curve: Some("secp384r1".to_string()), ..spec("mail") })
+            .unwrap();
+
+        assert_eq!(serial(&read_cert("mail")), "03");
+        assert_eq!(read_serials()[&intermediate].issued.len(), 3);
+
+        // A PKI saved without its index gets it back from its certificates
+        fs::remove_file(&serials_path).unwrap();
+
+        let mut manager = Pkimgr::new(configuration, base_path.clone());
+
+        manager.renew(&pki, &"web".to_string(), None).unwrap();
+
+        let serials = read_serials();
+
+        assert_eq!(serials[&intermediate].issued.len(), 4);
+        assert!(serials[&intermediate].issued.contains(&serial(&read_cert("web"))));
+        assert!(serials["Root"].issued.contains(&serial(&read_cert("Intermediate"))));
+
+        fs::remove_dir_all(base_path).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+mod verify_tests {
+    use openssl::x509::X509;
+    use pkimgr::{
+        certificates::{x509::create_x509_leaf, CertArgs, Subject},
+        key::Key,
+        pki::Pki,
+        report::VerifyFailure
+    };
+
+    use crate::common::{cert_args, configuration, key, new_pki, spec};
+
+    fn create_pki() -> Pki {
+        let mut pki = new_pki("verify");
+
+        pki.add_authority(&spec("Root"), None, key()).unwrap();
+        pki.add_authority(&spec("Intermediate"), Some(&"Root".to_string()), key()).unwrap();
+        pki.add_certificate(&spec("web"), &"Intermediate".to_string(), key()).unwrap();
+
+        pki
+    }
+
+    /// End entity certificate for `key`, claiming to be issued by `issuer` but signed with `signing_key`
+    fn leaf(name: &str, key: &Key, issuer: &X509, signing_key: &Key) -> X509 {
+        let configuration = configuration();
+
+        create_x509_leaf(CertArgs {
+            authority_issuer: Some(issuer.subject_name().to_owned().unwrap()),
+            subject: Subject::default().resolve(&configuration.x509_certs_entries),
+            authority_key_id: issuer.subject_key_id().map(|key_id| key_id.as_slice().to_vec()),
+            ..cert_args(name, key.to_public_pkey().unwrap(), signing_key.clone(), 10)
+        }).unwrap()
+    }
+
+    fn reasons(failures: &[VerifyFailure], cname: &str) -> Vec<String> {
+        failures.iter().filter(|failure| failure.cname == cname).map(|failure| failure.reason.clone()).collect()
+    }
+
+    #[test]
+    fn test_verify_forged_signature() {
+        let mut pki = create_pki();
+        let web = "web".to_string();
+        let intermediate = pki.get_cert(&"Intermediate".to_string()).unwrap().clone();
+        let (_, web_key) = pki.get_entry(&web).unwrap().clone();
+        let forged = leaf("web", &web_key, &intermediate, &key());
+
+        pki.certs.insert(web, (forged, web_key));
+
+        let failures = pki.verify().unwrap();
+
+        assert_eq!(reasons(&failures, "web"), vec!["signature does not verify with the key of its issuer"]);
+        assert_eq!(failures.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_issuer_not_ca() {
+        let mut pki = create_pki();
+        let name = "Intermediate".to_string();
+        let (root, root_key) = pki.get_entry(&"Root".to_string()).unwrap().clone();
+        let (_, intermediate_key) = pki.get_entry(&name).unwrap().clone();
+        let not_ca = leaf("Intermediate", &intermediate_key, &root, &root_key);
+
+        pki.authorities.insert(name, (not_ca, intermediate_key));
+
+        let failures = pki.verify().unwrap();
+
+        assert_eq!(reasons(&failures, "Intermediate"), vec!["authority is not a CA"]);
+        assert!(reasons(&failures, "web").contains(&"issuer is not a CA".to_string()));
+        assert!(reasons(&failures, "Root").is_empty());
+    }
+}
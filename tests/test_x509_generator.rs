@@ -1,6 +1,276 @@
+mod common;
+
 mod x509_unit_tests {
+    use std::collections::BTreeMap;
+
+    use openssl::{nid::Nid, x509::{extension::SubjectKeyIdentifier, X509}};
+    use pkimgr::{
+        certificates::{
+            signature::{Digest, Signature},
+            x509::{create_x509_leaf, create_x509_node, x509_to_certificate},
+            AltNames, CertArgs, KeyIdentifier, Profile, Subject, X509Info
+        },
+        key::Key
+    };
+
+    use crate::common::cert_args;
+
+    fn cert_entries() -> X509Info {
+        X509Info {
+            country: "UK".into(),
+            state: "UK".into(),
+            organization: "PKImgr".into(),
+            validity: 10
+        }
+    }
+
+    fn key_id(key: &Key) -> Vec<u8> {
+        KeyIdentifier::default().compute(&key.to_public_pkey().unwrap()).unwrap()
+    }
+
+    fn authority(key: &Key) -> CertArgs {
+        CertArgs {
+            subject: Subject::default().resolve(&cert_entries()),
+            ..cert_args("Root", key.to_public_pkey().unwrap(), key.clone(), 10)
+        }
+    }
+
     #[test]
     fn test_get_authorities() {
         assert_eq!(1, 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_leaf_alt_names() {
+        let root_key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let root = create_x509_node(authority(&root_key)).unwrap();
+        let key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let san = AltNames {
+            dns: vec!["api.internal".to_string()],
+            ip: vec!["10.0.0.1".to_string(), "::1".to_string()],
+            email: vec!["pki@example.com".to_string()],
+            uri: vec!["spiffe://example.com/api".to_string()]
+        };
+
+        let leaf = create_x509_leaf(CertArgs {
+            authority_issuer: Some(root.subject_name().to_owned().unwrap()),
+            subject: Subject::default().resolve(&cert_entries()),
+            authority_key_id: Some(key_id(&root_key)),
+            san: Some(san.clone()),
+            profile: Profile::Mtls,
+            ..cert_args("api", key.to_public_pkey().unwrap(), root_key.clone(), 10)
+        }).unwrap();
+
+        assert_eq!(x509_to_certificate(&leaf).unwrap().san, Some(san));
+        assert_eq!(x509_to_certificate(&root).unwrap().san, None);
+    }
+
+
+    #[test]
+    fn test_leaf_profiles() {
+        let root_key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let root = create_x509_node(authority(&root_key)).unwrap();
+
+        for (profile, key, expected) in [
+            (Profile::TlsServer, Key::new(Some(2048), None).unwrap(), "TLS Web Server Authentication"),
+            (Profile::CodeSigning, root_key.clone(), "Code Signing"),
+            (Profile::OcspSigning, root_key.clone(), "OCSP Signing"),
+        ] {
+            let leaf = create_x509_leaf(CertArgs {
+                authority_issuer: Some(root.subject_name().to_owned().unwrap()),
+                subject: Subject::default().resolve(&cert_entries()),
+                authority_key_id: Some(key_id(&root_key)),
+                profile,
+                ..cert_args(&profile.to_string(), key.to_public_pkey().unwrap(), root_key.clone(), 10)
+            }).unwrap();
+            let text = String::from_utf8(leaf.to_text().unwrap()).unwrap();
+
+            println!("{}", text);
+        assert!(text.contains("CA:FALSE"));
+            println!("{}", text);
+        assert!(text.contains(expected));
+            assert_eq!(text.contains("Key Encipherment"), matches!(key, Key::Rsa(_)));
+        }
+    }
+
+
+    #[test]
+    fn test_subject_attributes() {
+        let key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let subject = Subject {
+            organization: Some("Example".to_string()),
+            state: Some(String::new()),
+            locality: Some("London".to_string()),
+            organizational_unit: vec!["Ops".to_string(), "Web".to_string()],
+            email: Some("ops@example.com".to_string()),
+            serial_number: Some("42".to_string()),
+            domain_components: vec!["com".to_string(), "example".to_string()],
+            rdns: vec![BTreeMap::from([("street".to_string(), "1 Main Street".to_string())]), BTreeMap::from([
+                ("UID".to_string(), "ops".to_string()),
+                ("title".to_string(), "Operator".to_string())
+            ])],
+            ..Subject::default()
+        }.resolve(&cert_entries());
+
+        assert!(subject.validate().is_ok());
+
+        let root = create_x509_node(CertArgs { subject, ..authority(&key) }).unwrap();
+        let entries: Vec<String> = root.subject_name().entries()
+            .map(|entry| format!(
+                "{}={}",
+                entry.object().nid().short_name().unwrap(),
+                entry.data().as_utf8().unwrap()
+            ))
+            .collect();
+
+        assert_eq!(entries, [
+            "DC=com", "DC=example", "C=UK", "L=London", "O=Example", "OU=Ops", "OU=Web", "CN=Root",
+            "serialNumber=42", "emailAddress=ops@example.com", "street=1 Main Street", "title=Operator", "UID=ops"
+        ]);
+
+        // title and UID share the last RDN
+        let text = String::from_utf8(root.to_text().unwrap()).unwrap();
+
+        assert!(text.contains("/title=Operator+UID=ops"));
+    }
+
+    fn rdn(attributes: &[(&str, &str)]) -> BTreeMap<String, String> {
+        attributes.iter().map(|(field, value)| (field.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_multi_valued_rdn_replaces_defaults() {
+        let key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let subject = Subject { rdns: vec![rdn(&[("O", "Example"), ("OU", "Ops")])], ..Subject::default() }
+            .resolve(&cert_entries());
+
+        assert!(subject.validate().is_ok());
+        assert_eq!(subject.organization, None);
+
+        let root = create_x509_node(CertArgs { subject, ..authority(&key) }).unwrap();
+        let text = String::from_utf8(root.to_text().unwrap()).unwrap();
+
+        assert_eq!(root.subject_name().entries_by_nid(Nid::ORGANIZATIONNAME).count(), 1);
+        assert!(text.contains("CN=Root, OU=Ops+O=Example"));
+    }
+
+    #[test]
+    fn test_subject_validation() {
+        let invalid = [
+            Subject { country: Some("uk".to_string()), ..Subject::default() },
+            Subject { country: Some("GBR".to_string()), ..Subject::default() },
+            Subject { email: Some("ops".to_string()), ..Subject::default() },
+            Subject { domain_components: vec!["example.com".to_string()], ..Subject::default() },
+            Subject { serial_number: Some("#42".to_string()), ..Subject::default() },
+            Subject { rdns: vec![BTreeMap::new()], ..Subject::default() },
+            Subject { rdns: vec![rdn(&[("CN", "Root"), ("OU", "Ops")])], ..Subject::default() },
+            Subject { organization: Some("Example".to_string()), rdns: vec![rdn(&[("O", "Other"), ("OU", "Ops")])], ..Subject::default() },
+        ];
+
+        for subject in invalid {
+            assert!(subject.validate().is_err(), "{:?}", subject);
+        }
+
+        // An empty value removes the default attribute
+        let subject = Subject { country: Some(String::new()), ..Subject::default() }.resolve(&cert_entries());
+
+        assert_eq!(subject.country, None);
+        assert_eq!(subject.organization, Some("PKImgr".to_string()));
+    }
+
+    #[test]
+    fn test_signature_digests() {
+        let root_key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let root = create_x509_node(authority(&root_key)).unwrap();
+
+        // The default digest matches the key strength
+        assert_eq!(root.signature_algorithm().object().nid(), Nid::ECDSA_WITH_SHA384);
+
+        let rsa_key = Key::new(Some(2048), None).unwrap();
+        let signatures = [
+            (Signature { digest: Digest::Sha256, rsa_pss: false }, "sha256WithRSAEncryption"),
+            (Signature { digest: Digest::Sha3_512, rsa_pss: false }, "RSA-SHA3-512"),
+            (Signature { digest: Digest::Sha384, rsa_pss: true }, "rsassaPss"),
+        ];
+
+        for (signature, algorithm) in signatures {
+            let rsa_root = create_x509_node(CertArgs { signature, ..authority(&rsa_key) }).unwrap();
+            let text = String::from_utf8(rsa_root.to_text().unwrap()).unwrap();
+
+            assert_eq!(rsa_root.signature_algorithm().object().to_string(), algorithm);
+            assert!(rsa_root.verify(&rsa_key.to_public_pkey().unwrap()).unwrap());
+
+            if signature.rsa_pss {
+                assert!(text.contains("Hash Algorithm: sha384"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_eddsa_signatures() {
+        let root_key = Key::new(None, Some("ed25519".to_string())).unwrap();
+        let root = create_x509_node(authority(&root_key)).unwrap();
+        let key = Key::new(None, Some("ed448".to_string())).unwrap();
+        let leaf = create_x509_leaf(CertArgs {
+            authority_issuer: Some(root.subject_name().to_owned().unwrap()),
+            authority_pkey: root_key.clone(),
+            authority_key_id: Some(key_id(&root_key)),
+            name: "leaf".to_string(),
+            ..authority(&key)
+        }).unwrap();
+
+        assert_eq!(root.signature_algorithm().object().to_string(), "ED25519");
+        assert!(root.verify(&root_key.to_public_pkey().unwrap()).unwrap());
+        assert!(leaf.verify(&root_key.to_public_pkey().unwrap()).unwrap());
+        assert_eq!(x509_to_certificate(&leaf).unwrap().curve.as_deref(), Some("ed448"));
+    }
+
+
+    #[test]
+    fn test_invalid_common_name() {
+        let key = Key::new(None, Some("prime256v1".to_string())).unwrap();
+        let mut der = create_x509_node(CertArgs { name: "zzzz".to_string(), ..authority(&key) }).unwrap().to_der().unwrap();
+
+        // Turn the issuer and subject common name UTF8String into an ObjectDescriptor, which
+        // openssl cannot convert to UTF-8. The signature is not checked.
+
+        while let Some(position) = der.windows(6).position(|window| window == b"\x0c\x04zzzz") {
+            der[position] = 0x07;
+        }
+
+        assert!(x509_to_certificate(&X509::from_der(&der).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_key_identifiers() {
+        let root_key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let root = create_x509_node(authority(&root_key)).unwrap();
+        let key = Key::new(None, Some("secp384r1".to_string())).unwrap();
+        let leaf = create_x509_leaf(CertArgs {
+            authority_issuer: Some(root.subject_name().to_owned().unwrap()),
+            authority_pkey: root_key.clone(),
+            authority_key_id: Some(key_id(&root_key)),
+            name: "leaf".to_string(),
+            ..authority(&key)
+        }).unwrap();
+
+        // Same identifier as the openssl "hash" method
+        let mut builder = X509::builder().unwrap();
+        builder.set_pubkey(&root_key.to_public_pkey().unwrap()).unwrap();
+        let ski = SubjectKeyIdentifier::new().build(&builder.x509v3_context(None, None)).unwrap();
+        builder.append_extension(ski).unwrap();
+        let openssl_ski = builder.build();
+
+        assert_eq!(root.subject_key_id().unwrap().as_slice(), openssl_ski.subject_key_id().unwrap().as_slice());
+        assert!(root.authority_key_id().is_none());
+        assert_eq!(leaf.authority_key_id().unwrap().as_slice(), root.subject_key_id().unwrap().as_slice());
+
+        let public_key = key.to_public_pkey().unwrap();
+        let short = KeyIdentifier::Sha1Short.compute(&public_key).unwrap();
+
+        assert_eq!(short.len(), 8);
+        assert_eq!(short[0] & 0xf0, 0x40);
+        assert_eq!(KeyIdentifier::Sha256.compute(&public_key).unwrap().len(), 20);
+        assert_ne!(KeyIdentifier::Sha256.compute(&public_key).unwrap(), key_id(&key));
+    }
+}